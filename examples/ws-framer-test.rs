@@ -1,13 +1,8 @@
-use core::str;
-use std::{
-    io::{Read, Write},
-    net::{TcpListener, TcpStream},
-};
+use std::net::{TcpListener, TcpStream};
 
 use anyhow::Result;
 use clap::Parser;
-use httparse::Header;
-use ws_framer::{WsRxFramer, WsTxFramer};
+use ws_framer::{AcceptOptions, ConnectionError, WsFrame, WsStream, WsUrl};
 
 #[derive(Parser, Debug)]
 #[command(version)]
@@ -41,65 +36,23 @@ pub fn start_server(ip: &str) -> Result<()> {
     let listener = TcpListener::bind(ip)?;
     println!("Server started on: {ip:?}!");
 
-    while let Ok((mut stream, addr)) = listener.accept() {
+    while let Ok((stream, addr)) = listener.accept() {
         println!("Client connected: {addr}");
 
         let mut rx_buf = vec![0; 10240];
         let mut tx_buf = vec![0; 10240];
-        let mut rx_framer = WsRxFramer::new(&mut rx_buf);
-        let mut tx_framer = WsTxFramer::new(false, &mut tx_buf);
-
-        let mut buf = [0; 4096];
-        let n = stream.read(&mut buf)?;
-
-        let mut headers = [httparse::EMPTY_HEADER; 16];
-        let mut req = httparse::Request::new(&mut headers);
-        let res = req
-            .parse(&buf[..n])
-            .map_err(|e| anyhow::anyhow!("parse err: {e:?}"))?;
-
-        if res.is_partial() {
-            println!("[ERROR] HTTP request not complete (partial)");
-            continue;
-        }
-
-        let sec_ws_key = core::str::from_utf8(
-            req.headers
-                .iter()
-                .find(|x| x.name == "Sec-WebSocket-Key")
-                .unwrap()
-                .value,
-        )?;
-        println!("sec_ws_key: {sec_ws_key}");
-
-        let headers = [
-            Header {
-                name: "Upgrade",
-                value: b"websocket",
-            },
-            Header {
-                name: "Connection",
-                value: b"upgrade",
-            },
-            Header {
-                name: "Sec-WebSocket-Accept",
-                value: &ws_framer::process_sec_websocket_key(sec_ws_key),
-            },
-        ];
-
-        stream.write_all(tx_framer.generate_http_response(101, "Switching Protocols", &headers))?;
-        stream.write_all(&tx_framer.text("Hello"))?;
-        loop {
-            let read_n = stream.read(rx_framer.mut_buf())?;
-            if read_n == 0 {
-                break;
+        let options = AcceptOptions::default();
+        let mut ws = match WsStream::accept(stream, &options, &mut rx_buf, &mut tx_buf) {
+            Ok(ws) => ws,
+            Err(err) => {
+                println!("[ERROR] Handshake failed: {err}");
+                continue;
             }
+        };
 
-            rx_framer.revolve_write_offset(read_n);
-            while let Some(frame) = rx_framer.process_data() {
-                println!("{frame:?}");
-                stream.write_all(&tx_framer.frame(frame))?;
-            }
+        ws.write_message(WsFrame::Text("Hello"))?;
+        if let Err(err) = echo(&mut ws) {
+            println!("[ERROR] {err}");
         }
     }
 
@@ -109,53 +62,33 @@ pub fn start_server(ip: &str) -> Result<()> {
 pub fn start_client(ip: &str) -> Result<()> {
     let mut rx_buf = vec![0; 10240];
     let mut tx_buf = vec![0; 10240];
-    let mut rx_framer = WsRxFramer::new(&mut rx_buf);
-    let mut tx_framer = WsTxFramer::new(true, &mut tx_buf);
 
-    let mut client = TcpStream::connect(ip)?;
-    client.write_all(&tx_framer.generate_http_upgrade("debica.fkmtime.com", "/?id=2469858181&ver=D1737832232&chip=esp32c3&firmware=STATION", None))?;
-    loop {
-        let n = client.read(rx_framer.mut_buf())?;
-        let res = rx_framer.process_http_response(n);
+    let url = format!("ws://{ip}/");
+    let url = WsUrl::from_str(&url).ok_or(anyhow::anyhow!("invalid url"))?;
+    let stream = TcpStream::connect(ip)?;
+    let mut ws = WsStream::connect(stream, &url, &mut rx_buf, &mut tx_buf)?;
+    println!("Connected to: {ip}");
 
-        if let Some(res) = res {
-            let code = res.status_code;
-            println!("http_resp_code: {code}");
-            break;
-        }
-    }
-
-    /*
-    let mut buf = Vec::new();
-    buf.extend_from_slice(&tx_framer.text("Hello"));
-    buf.extend_from_slice(&tx_framer.text("Friend"));
-    buf.extend_from_slice(&tx_framer.ping(&[]));
-    client.write_all(&buf)?;
-
-    std::thread::sleep(std::time::Duration::from_secs(1));
-    client.write_all(&tx_framer.close(1000, "Connection closed!"))?;
-    Ok(())
-    */
+    echo(&mut ws)
+}
 
+/// Echo every received frame until connection is closed
+fn echo(ws: &mut WsStream<'_, TcpStream>) -> Result<()> {
     loop {
-        loop {
-            let read_n = client.read(rx_framer.mut_buf())?;
-            if read_n == 0 {
-                break;
-            }
-
-            rx_framer.revolve_write_offset(read_n);
-            let res = rx_framer.process_data();
-            if res.is_some() {
-                println!("{res:?}");
-                client.write_all(&tx_framer.frame(res.unwrap()))?;
-            }
+        let frame = match ws.read_message() {
+            Ok(frame) => frame,
+            Err(ConnectionError::Closed) => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+
+        println!("{frame:?}");
+        let close = matches!(frame, WsFrame::Close(..));
+
+        // frame borrows stream buffer, so it must be copied before writing
+        let frame = frame.into_owned();
+        ws.write_message(frame.into_ref())?;
+        if close {
+            return Ok(());
         }
-        //client.write_all(&tx_framer.text("Lorem"))?;
-        std::thread::sleep(std::time::Duration::from_secs(1));
     }
-
-    //std::thread::sleep(std::time::Duration::from_secs(1));
-    //client.write_all(&tx_framer.close(1000))?;
-    //Ok(())
 }
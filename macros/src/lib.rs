@@ -92,7 +92,7 @@ pub fn base64_impl(item: TokenStream) -> TokenStream {
 
     let encode_len_tokens = match pad {
         true => quote! {
-            (n + 2) / 3 * 4
+            n.div_ceil(3) * 4
         },
         false => quote! {
             n / 3 * 4 + (n % 3 * 4 + 2) / 3
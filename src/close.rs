@@ -0,0 +1,117 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Websocket close frame status code
+pub enum CloseCode {
+    /// 1000 - normal closure
+    Normal,
+
+    /// 1001 - endpoint is going away (server shutdown, page navigation)
+    GoingAway,
+
+    /// 1002 - protocol error
+    ProtocolError,
+
+    /// 1003 - received data type that cannot be accepted
+    Unsupported,
+
+    /// 1005 - close frame without status code (never sent on the wire)
+    NoStatus,
+
+    /// 1007 - message data inconsistent with its type (eg. invalid utf-8)
+    InvalidPayload,
+
+    /// 1008 - message violates endpoint policy
+    PolicyViolation,
+
+    /// 1009 - message too big to process
+    MessageTooBig,
+
+    /// 1010 - client expected server to negotiate extension
+    MandatoryExtension,
+
+    /// 1011 - server encountered unexpected condition
+    InternalError,
+
+    /// 3000-3999 - codes registered by libraries and frameworks
+    Library(u16),
+
+    /// 4000-4999 - codes for private use
+    Private(u16),
+}
+
+impl CloseCode {
+    /// Parse numeric status code
+    ///
+    /// Returns `None` for codes that are reserved or unassigned
+    pub fn from_u16(code: u16) -> Option<Self> {
+        Some(match code {
+            1000 => CloseCode::Normal,
+            1001 => CloseCode::GoingAway,
+            1002 => CloseCode::ProtocolError,
+            1003 => CloseCode::Unsupported,
+            1005 => CloseCode::NoStatus,
+            1007 => CloseCode::InvalidPayload,
+            1008 => CloseCode::PolicyViolation,
+            1009 => CloseCode::MessageTooBig,
+            1010 => CloseCode::MandatoryExtension,
+            1011 => CloseCode::InternalError,
+            3000..=3999 => CloseCode::Library(code),
+            4000..=4999 => CloseCode::Private(code),
+            _ => return None,
+        })
+    }
+
+    /// Numeric status code
+    pub fn as_u16(&self) -> u16 {
+        match self {
+            CloseCode::Normal => 1000,
+            CloseCode::GoingAway => 1001,
+            CloseCode::ProtocolError => 1002,
+            CloseCode::Unsupported => 1003,
+            CloseCode::NoStatus => 1005,
+            CloseCode::InvalidPayload => 1007,
+            CloseCode::PolicyViolation => 1008,
+            CloseCode::MessageTooBig => 1009,
+            CloseCode::MandatoryExtension => 1010,
+            CloseCode::InternalError => 1011,
+            CloseCode::Library(code) | CloseCode::Private(code) => *code,
+        }
+    }
+
+    /// If code can be sent inside close frame
+    pub fn is_allowed_on_wire(&self) -> bool {
+        match self {
+            CloseCode::NoStatus => false,
+            CloseCode::Library(code) => (3000..=3999).contains(code),
+            CloseCode::Private(code) => (4000..=4999).contains(code),
+            _ => true,
+        }
+    }
+}
+
+impl From<CloseCode> for u16 {
+    fn from(code: CloseCode) -> Self {
+        code.as_u16()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_close_codes() {
+        for code in [1000, 1001, 1002, 1003, 1007, 1011, 3000, 3999, 4000, 4999] {
+            let close_code = CloseCode::from_u16(code).unwrap();
+            assert_eq!(close_code.as_u16(), code);
+            assert!(close_code.is_allowed_on_wire());
+        }
+
+        for code in [0, 999, 1004, 1006, 1012, 1015, 1100, 2999, 5000] {
+            assert_eq!(CloseCode::from_u16(code), None);
+        }
+
+        assert!(!CloseCode::NoStatus.is_allowed_on_wire());
+        assert!(!CloseCode::Library(1000).is_allowed_on_wire());
+        assert!(!CloseCode::Private(3000).is_allowed_on_wire());
+    }
+}
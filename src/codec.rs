@@ -0,0 +1,302 @@
+use crate::{
+    framer::{frame_parts, write_packet},
+    ConnectionError, RxRules, Utf8Validator, WsError, WsFrame, WsFrameHeader, WsFrameOwned,
+};
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Websocket codec for `tokio_util::codec::Framed`
+///
+/// Uses the same header parsing and packet encoding as `WsRxFramer` and
+/// `WsTxFramer`. Fragmented messages are reassembled and yielded as single
+/// `Text`/`Binary` frame, control frames interleaved between fragments are
+/// yielded immediately.
+#[derive(Debug)]
+pub struct WsCodec {
+    mask: bool,
+    rules: RxRules,
+    max_message_len: usize,
+    message_opcode: Option<u8>,
+    message: BytesMut,
+    utf8: Utf8Validator,
+}
+
+impl WsCodec {
+    /// Create new codec, `mask` should be set for client side
+    ///
+    /// Received frames must use opposite masking (server receives masked frames)
+    pub fn new(mask: bool) -> Self {
+        Self {
+            mask,
+            rules: RxRules {
+                expect_mask: Some(!mask),
+                ..RxRules::default()
+            },
+            max_message_len: crate::consts::CODEC_MAX_MESSAGE_LEN,
+            message_opcode: None,
+            message: BytesMut::new(),
+            utf8: Utf8Validator::new(),
+        }
+    }
+
+    /// Set maximum length of received frame (or reassembled message) payload
+    pub fn with_max_message_len(mut self, max_message_len: usize) -> Self {
+        self.max_message_len = max_message_len;
+        self
+    }
+
+    /// Set maximum payload length of control frame (see
+    /// `WsRxFramer::with_max_control_len`)
+    pub fn with_max_control_len(mut self, max_control_len: usize) -> Self {
+        self.rules.max_control_len = max_control_len;
+        self
+    }
+
+    /// Enable or disable strict validation of frame headers (see
+    /// `WsRxFramer::with_strict`)
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.rules.strict = strict;
+        self
+    }
+}
+
+impl Decoder for WsCodec {
+    type Item = WsFrameOwned;
+    type Error = ConnectionError<std::io::Error>;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            let Some(header) = WsFrameHeader::parse(src, self.rules.strict)? else {
+                return Ok(None);
+            };
+
+            // no extensions are supported, so every RSV bit is rejected
+            header.validate(&self.rules, 0)?;
+            let is_data = header.opcode < 8;
+            if is_data
+                && header.payload_len > self.max_message_len.saturating_sub(self.message.len())
+            {
+                return Err(match self.message_opcode {
+                    Some(_) => WsError::MessageTooLarge,
+                    None => WsError::FrameTooLarge,
+                }
+                .into());
+            }
+
+            let frame_len = header.offset + header.payload_len;
+            if src.len() < frame_len {
+                src.reserve(frame_len - src.len());
+                return Ok(None);
+            }
+
+            let mut packet = src.split_to(frame_len);
+            let payload = &mut packet[header.offset..];
+            if !is_data {
+                return Ok(Some(WsFrame::from_data(&header, payload)?.into_owned()));
+            }
+
+            header.check_sequence(self.message_opcode)?;
+            match self.message_opcode {
+                None if header.fin => {
+                    return Ok(Some(WsFrame::from_data(&header, payload)?.into_owned()));
+                }
+                None => {
+                    self.message_opcode = Some(header.opcode);
+                    self.utf8.reset();
+                }
+                Some(_) => {}
+            }
+
+            if header.mask {
+                crate::apply_mask(payload, header.masking_key, 0);
+            }
+
+            // validate text fragments as they arrive (fail fast)
+            if self.message_opcode == Some(1) {
+                self.utf8.feed(payload)?;
+            }
+
+            self.message.extend_from_slice(payload);
+            if header.fin {
+                if self.message_opcode == Some(1) {
+                    self.utf8.finish()?;
+                }
+
+                let mut message = self.message.split();
+                let header = WsFrameHeader {
+                    fin: true,
+                    rsv1: false,
+                    rsv2: false,
+                    rsv3: false,
+                    opcode: self.message_opcode.take().unwrap_or_default(),
+                    mask: false,
+                    masking_key: [0; 4],
+                    payload_len: message.len(),
+                    offset: 0,
+                };
+
+                return Ok(Some(
+                    WsFrame::from_data(&header, &mut message)?.into_owned(),
+                ));
+            }
+        }
+    }
+}
+
+impl Encoder<WsFrame<'_>> for WsCodec {
+    type Error = ConnectionError<std::io::Error>;
+
+    fn encode(&mut self, frame: WsFrame<'_>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let mut masking_key = [0; 4];
+        if self.mask {
+            crate::rng_fill(&mut masking_key);
+        }
+
+        let mut code = [0; 2];
+        let parts = frame_parts(&frame, &mut code)?;
+        let header = WsFrameHeader {
+            fin: true,
+            rsv1: false,
+            rsv2: false,
+            rsv3: false,
+            opcode: frame.opcode(),
+            mask: self.mask,
+            masking_key,
+            payload_len: parts[0].len() + parts[1].len(),
+            offset: 0,
+        };
+
+        let start = dst.len();
+        let len = WsFrameHeader::header_len(header.payload_len, header.mask) + header.payload_len;
+        dst.resize(start + len, 0);
+        if let Err(err) = write_packet(&mut dst[start..], &header, &parts, &mut []) {
+            // partially written packet must not be flushed
+            dst.truncate(start);
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+}
+
+impl Encoder<WsFrameOwned> for WsCodec {
+    type Error = ConnectionError<std::io::Error>;
+
+    fn encode(&mut self, frame: WsFrameOwned, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode(frame.into_ref(), dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CloseCode;
+
+    #[test]
+    fn decode_fragmented() {
+        let mut codec = WsCodec::new(false);
+        let mut src = BytesMut::new();
+
+        // masked fragment ("hel" with key 01 02 03 04), empty ping and final
+        // continuation ("lo" with zero key), delivered byte by byte
+        let data = b"\x01\x83\x01\x02\x03\x04\x69\x67\x6f\x89\x80\0\0\0\0\x80\x82\0\0\0\0lo";
+
+        let mut frames = std::vec::Vec::new();
+        for byte in data.chunks(1) {
+            src.extend_from_slice(byte);
+            while let Some(frame) = codec.decode(&mut src).unwrap() {
+                frames.push(frame);
+            }
+        }
+
+        assert_eq!(
+            frames,
+            [
+                WsFrameOwned::Ping(std::vec::Vec::new()),
+                WsFrameOwned::Text("hello".into())
+            ]
+        );
+        assert!(src.is_empty());
+
+        src.extend_from_slice(&[0x80, 0x80, 0, 0, 0, 0]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(ConnectionError::Ws(WsError::UnexpectedContinuation))
+        ));
+    }
+
+    #[test]
+    fn decode_rules() {
+        // control frame doesn't count towards message limit
+        let mut codec = WsCodec::new(true).with_max_message_len(4);
+        let mut src = BytesMut::from(&b"\x01\x04abcd\x89\x02pq\x80\x00"[..]);
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            Some(WsFrameOwned::Ping(b"pq".to_vec()))
+        );
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            Some(WsFrameOwned::Text("abcd".into()))
+        );
+
+        // invalid utf-8 is rejected before message is complete
+        src.extend_from_slice(b"\x01\x01\xFF");
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(ConnectionError::Ws(WsError::InvalidUtf8))
+        ));
+
+        let mut codec = WsCodec::new(true).with_max_control_len(1);
+        let mut src = BytesMut::from(&b"\x89\x02pq"[..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(ConnectionError::Ws(WsError::ControlFrameTooLarge))
+        ));
+
+        // lenient mode ignores RSV bits
+        let mut codec = WsCodec::new(true).with_strict(false);
+        let mut src = BytesMut::from(&b"\xC2\x01\x01"[..]);
+        assert_eq!(
+            codec.decode(&mut src).unwrap(),
+            Some(WsFrameOwned::Binary(std::vec![1]))
+        );
+    }
+
+    #[test]
+    fn encode_roundtrip() {
+        let mut client = WsCodec::new(true);
+        let mut server = WsCodec::new(false).with_max_message_len(300);
+        let mut buf = BytesMut::new();
+
+        let payload = [7; 300];
+        client.encode(WsFrame::Binary(&payload), &mut buf).unwrap();
+        client
+            .encode(WsFrame::Close(CloseCode::Normal, "bye"), &mut buf)
+            .unwrap();
+        assert_eq!(buf.len(), 8 + 300 + 6 + 5);
+
+        assert_eq!(
+            server.decode(&mut buf).unwrap(),
+            Some(WsFrameOwned::Binary(payload.into()))
+        );
+        assert_eq!(
+            server.decode(&mut buf).unwrap(),
+            Some(WsFrameOwned::Close(CloseCode::Normal, "bye".into()))
+        );
+
+        // failed encode leaves buffer unchanged
+        client.encode(WsFrame::Ping(b"p"), &mut buf).unwrap();
+        assert!(client.encode(WsFrame::Ping(&[0; 200]), &mut buf).is_err());
+        assert_eq!(buf.len(), 7);
+        assert_eq!(
+            server.decode(&mut buf).unwrap(),
+            Some(WsFrameOwned::Ping(b"p".to_vec()))
+        );
+
+        client.encode(WsFrame::Binary(&[0; 301]), &mut buf).unwrap();
+        assert!(matches!(
+            server.decode(&mut buf),
+            Err(ConnectionError::Ws(WsError::FrameTooLarge))
+        ));
+    }
+}
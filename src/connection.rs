@@ -0,0 +1,146 @@
+use crate::{driver::WsDriver, AcceptOptions, ConnectionError, WsFrame, WsUrl};
+use embedded_io_async::{Read, Write};
+
+/// Async websocket connection over `embedded-io-async` transport
+///
+/// Uses caller-provided buffers, fragmented messages are reassembled, so
+/// `rx_buf` must be large enough to hold whole message.
+pub struct WsConnection<'a, T> {
+    io: T,
+    driver: WsDriver<'a>,
+}
+
+impl<'a, T: Read + Write> WsConnection<'a, T> {
+    /// Send upgrade request to server and wait for valid handshake response
+    pub async fn connect(
+        io: T,
+        url: &WsUrl<'_>,
+        rx_buf: &'a mut [u8],
+        tx_buf: &'a mut [u8],
+    ) -> Result<Self, ConnectionError<T::Error>> {
+        let mut conn = Self {
+            io,
+            driver: WsDriver::client(rx_buf, tx_buf),
+        };
+
+        let request = conn.driver.upgrade_request(url)?;
+        write(&mut conn.io, request).await?;
+        while !conn.driver.handshake_response()? {
+            read(&mut conn.io, &mut conn.driver).await?;
+        }
+
+        Ok(conn)
+    }
+
+    /// Wait for client upgrade request and respond to it
+    ///
+    /// Rejected request is answered with error response before returning error
+    pub async fn accept(
+        io: T,
+        options: &AcceptOptions<'_>,
+        rx_buf: &'a mut [u8],
+        tx_buf: &'a mut [u8],
+    ) -> Result<Self, ConnectionError<T::Error>> {
+        let mut conn = Self {
+            io,
+            driver: WsDriver::server(rx_buf, tx_buf),
+        };
+
+        loop {
+            let Some((response, result)) = conn.driver.accept(options)? else {
+                read(&mut conn.io, &mut conn.driver).await?;
+                continue;
+            };
+
+            write(&mut conn.io, response).await?;
+            result?;
+            return Ok(conn);
+        }
+    }
+
+    /// Receive next frame (waits until whole frame is received)
+    pub async fn recv(&mut self) -> Result<WsFrame<'_>, ConnectionError<T::Error>> {
+        loop {
+            if let Some(parsed) = self.driver.next_frame()? {
+                return Ok(self.driver.parsed_frame(parsed)?);
+            }
+
+            read(&mut self.io, &mut self.driver).await?;
+        }
+    }
+
+    /// Send frame and flush transport
+    pub async fn send(&mut self, frame: WsFrame<'_>) -> Result<(), ConnectionError<T::Error>> {
+        let data = self.driver.frame(frame)?;
+        write(&mut self.io, data).await
+    }
+
+    /// Return inner transport
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+/// Read from transport into free space of driver buffer
+async fn read<T: Read>(
+    io: &mut T,
+    driver: &mut WsDriver<'_>,
+) -> Result<(), ConnectionError<T::Error>> {
+    let n = io
+        .read(driver.read_buf()?)
+        .await
+        .map_err(ConnectionError::Io)?;
+    driver.received(n)
+}
+
+/// Write whole data to transport and flush it
+async fn write<T: Write>(io: &mut T, data: &[u8]) -> Result<(), ConnectionError<T::Error>> {
+    io.write_all(data).await.map_err(ConnectionError::Io)?;
+    io.flush().await.map_err(ConnectionError::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::mock::MockIo;
+    use core::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = pin!(fut);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn accept_and_echo() {
+        let input = b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+            Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+            Sec-WebSocket-Version: 13\r\n\r\n\x01\x82\0\0\0\0he\x89\x80\0\0\0\0\x80\x83\0\0\0\0llo";
+        // deliver data in small pieces to exercise partial reads
+        let io = MockIo::new(input, 50);
+
+        let mut rx_buf = [0; 256];
+        let mut tx_buf = [0; 256];
+        block_on(async {
+            let options = AcceptOptions::default();
+            let mut conn = WsConnection::accept(io, &options, &mut rx_buf, &mut tx_buf)
+                .await
+                .unwrap();
+
+            assert_eq!(conn.recv().await, Ok(WsFrame::Ping(&[])));
+            assert_eq!(conn.recv().await, Ok(WsFrame::Text("hello")));
+            assert_eq!(conn.recv().await, Err(ConnectionError::Closed));
+            conn.send(WsFrame::Text("hi")).await.unwrap();
+
+            conn.into_inner().check_output(b"\r\n\r\n\x81\x02hi");
+        });
+    }
+}
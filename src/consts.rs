@@ -20,8 +20,12 @@ pub const WS_DEFAULT_CONNECT_HEADERS: [Header; 3] = [
     },
 ];
 
+#[cfg(feature = "http")]
+/// Maximum number of headers parsed from http request
+pub const HTTP_MAX_HEADERS: usize = 24;
+
 /// Guid that is concatinated with Sec-WebSocket-Key header (by server)
-pub const WS_KEY_GUID: &'static str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+pub const WS_KEY_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
 
 /// Random websocket key length (in base64 chars)
 pub const WS_KEY_B64_LEN: usize = Base64Pad::encode_len(16);
@@ -35,4 +39,8 @@ pub const SHA1_BLOCKS_LEN: usize = crate::crypto::sha1_blocks_len(WS_HASH_LEN);
 /// Output size of Sec-WebSocket-Accept (in base64 form)
 pub const PROCESSED_WS_KEY_B64_LEN: usize = Base64Pad::encode_len(20);
 
+#[cfg(feature = "tokio")]
+/// Default maximum payload length of message received by `WsCodec`
+pub const CODEC_MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
 pub const U16_MAX: usize = u16::MAX as usize;
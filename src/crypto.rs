@@ -7,12 +7,6 @@ base64_impl!(
     false
 );
 
-macro_rules! left_rotate {
-    ($x:expr,$c:expr) => {
-        ($x << $c) | ($x >> (32 - $c))
-    };
-}
-
 #[inline(always)]
 fn f(t: u32, b: u32, c: u32, d: u32) -> u32 {
     match t {
@@ -50,14 +44,14 @@ pub fn sha1(input: &mut [u8], len: usize) -> [u8; 20] {
         unsafe { core::slice::from_raw_parts(input.as_ptr() as *const u32, blocks_len / 4) };
 
     let mut w = [0u32; 80];
-    let mut h = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
     for chunk in blocks.chunks(16) {
-        for t in 0..16 {
-            w[t] = u32::from_be(chunk[t]);
+        for (w, &x) in w.iter_mut().zip(chunk) {
+            *w = u32::from_be(x);
         }
 
         for t in 16..80 {
-            w[t] = left_rotate!(w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16], 1);
+            w[t] = (w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16]).rotate_left(1);
         }
 
         let mut a = h[0];
@@ -65,16 +59,17 @@ pub fn sha1(input: &mut [u8], len: usize) -> [u8; 20] {
         let mut c = h[2];
         let mut d = h[3];
         let mut e = h[4];
-        for t in 0..80usize {
-            let temp = (left_rotate!(a, 5) as u32)
+        for (t, &w) in w.iter().enumerate() {
+            let temp = a
+                .rotate_left(5)
                 .wrapping_add(f(t as u32, b, c, d))
                 .wrapping_add(e)
-                .wrapping_add(w[t])
+                .wrapping_add(w)
                 .wrapping_add(k(t as u32));
 
             e = d;
             d = c;
-            c = left_rotate!(b, 30);
+            c = b.rotate_left(30);
             b = a;
             a = temp;
         }
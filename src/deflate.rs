@@ -0,0 +1,590 @@
+use crate::{HandshakeError, WsError};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::fmt::Write;
+use miniz_oxide::{
+    deflate::core::{create_comp_flags_from_zip_params, CompressionStrategy, CompressorOxide},
+    inflate::stream::InflateState,
+    DataFormat, MZError, MZFlush,
+};
+
+/// Extension token used in Sec-WebSocket-Extensions header
+const EXTENSION_NAME: &str = "permessage-deflate";
+
+/// Bytes removed from the end of every compressed message (empty stored block)
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xFF, 0xFF];
+
+/// Output buffer growth step used while (de)compressing
+const CHUNK_SIZE: usize = 1024;
+
+/// Compression level used for outgoing messages
+const COMPRESSION_LEVEL: i32 = 6;
+
+/// Memory allocated by compressor besides `CompressorOxide` itself (huffman
+/// tables, output buffer, 32KiB dictionary with lookahead and hash chains of
+/// miniz_oxide 0.8)
+const DEFLATE_HEAP_MEMORY: usize =
+    (3 * 288) * 5 + (64 * 1024 * 13 / 10) + (32768 + 258) + 2 * 32768 * 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Parameters of permessage-deflate extension (RFC 7692)
+///
+/// Used as client offer (see `DeflateParams::offer`), as server limits
+/// (`AcceptOptions::deflate`) and as result of negotiation that is passed to
+/// `WsRxFramer::enable_deflate` and `WsTxFramer::enable_deflate`.
+///
+/// Window bits are honoured on the wire only: miniz_oxide always allocates
+/// 32KiB windows (smaller compressor window is emulated by run-length
+/// encoding). Memory of (de)compressor state is bounded by `max_memory`
+/// instead.
+pub struct DeflateParams {
+    /// Server resets compression context after every message
+    pub server_no_context_takeover: bool,
+
+    /// Client resets compression context after every message
+    pub client_no_context_takeover: bool,
+
+    /// Size of LZ77 window (8-15 bits) used by server compressor
+    pub server_max_window_bits: u8,
+
+    /// Size of LZ77 window (8-15 bits) used by client compressor
+    pub client_max_window_bits: u8,
+
+    /// Memory available for (de)compressor state of this endpoint (not sent
+    /// on the wire, unlimited by default)
+    ///
+    /// Extension isn't offered or accepted if decompressor doesn't fit
+    /// (`INFLATE_MEMORY`). Messages are sent uncompressed if compressor
+    /// doesn't fit too (`INFLATE_MEMORY + DEFLATE_MEMORY`).
+    pub max_memory: usize,
+}
+
+impl Default for DeflateParams {
+    fn default() -> Self {
+        Self {
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+            server_max_window_bits: 15,
+            client_max_window_bits: 15,
+            max_memory: usize::MAX,
+        }
+    }
+}
+
+impl DeflateParams {
+    /// Memory allocated by decompressor of received messages
+    pub const INFLATE_MEMORY: usize = core::mem::size_of::<InflateState>();
+
+    /// Memory allocated by compressor of sent messages
+    pub const DEFLATE_MEMORY: usize = core::mem::size_of::<CompressorOxide>() + DEFLATE_HEAP_MEMORY;
+
+    /// Value of Sec-WebSocket-Extensions header sent in client upgrade request
+    ///
+    /// `client_max_window_bits` is always offered, so server can limit window
+    /// used by client compressor. Returns `None` if decompressor doesn't fit
+    /// into `max_memory`.
+    pub fn offer(&self) -> Option<String> {
+        self.can_inflate().then(|| self.format(true))
+    }
+
+    /// If decompressor fits into `max_memory`
+    pub(crate) fn can_inflate(&self) -> bool {
+        self.max_memory >= Self::INFLATE_MEMORY
+    }
+
+    /// If both decompressor and compressor fit into `max_memory`
+    pub(crate) fn can_deflate(&self) -> bool {
+        self.can_inflate() && self.max_memory - Self::INFLATE_MEMORY >= Self::DEFLATE_MEMORY
+    }
+
+    /// Value of Sec-WebSocket-Extensions header sent in server response
+    pub(crate) fn response(&self) -> String {
+        self.format(false)
+    }
+
+    fn format(&self, offer: bool) -> String {
+        let mut value = String::from(EXTENSION_NAME);
+        if self.server_no_context_takeover {
+            value.push_str("; server_no_context_takeover");
+        }
+
+        if self.client_no_context_takeover {
+            value.push_str("; client_no_context_takeover");
+        }
+
+        if self.server_max_window_bits < 15 {
+            _ = write!(
+                value,
+                "; server_max_window_bits={}",
+                self.server_max_window_bits
+            );
+        }
+
+        if self.client_max_window_bits < 15 {
+            _ = write!(
+                value,
+                "; client_max_window_bits={}",
+                self.client_max_window_bits
+            );
+        } else if offer {
+            value.push_str("; client_max_window_bits");
+        }
+
+        value
+    }
+
+    /// Select first acceptable client offer (server side)
+    ///
+    /// Invalid offers are declined, window bits are limited to values from
+    /// server configuration. All offers are declined if decompressor doesn't
+    /// fit into `max_memory` of configuration.
+    pub(crate) fn negotiate<'a>(
+        offers: impl Iterator<Item = &'a str>,
+        config: &DeflateParams,
+    ) -> Option<DeflateParams> {
+        if !config.can_inflate() {
+            return None;
+        }
+
+        offers
+            .filter_map(|offer| parse(offer, true).ok().flatten())
+            .map(|(offer, client_max_window_bits)| DeflateParams {
+                server_no_context_takeover: offer.server_no_context_takeover
+                    || config.server_no_context_takeover,
+                client_no_context_takeover: offer.client_no_context_takeover
+                    || config.client_no_context_takeover,
+                server_max_window_bits: offer
+                    .server_max_window_bits
+                    .min(config.server_max_window_bits),
+                // client window can be limited only if client offered this parameter
+                client_max_window_bits: match client_max_window_bits {
+                    true => offer
+                        .client_max_window_bits
+                        .min(config.client_max_window_bits),
+                    false => 15,
+                },
+                max_memory: config.max_memory,
+            })
+            .next()
+    }
+
+    /// Parse permessage-deflate parameters accepted by server (client side)
+    ///
+    /// Fails if extension wasn't offered (`offer` is `None`), was accepted
+    /// multiple times or with invalid parameters (or parameters that don't
+    /// match offer). Other extensions are checked by `negotiate_extensions`
+    /// of framers.
+    pub(crate) fn from_response<'a>(
+        extensions: impl Iterator<Item = &'a str>,
+        offer: Option<&DeflateParams>,
+    ) -> Result<Option<DeflateParams>, HandshakeError> {
+        let mut extensions = extensions
+            .filter(|extension| extension.split(';').next().map(str::trim) == Some(EXTENSION_NAME));
+        let Some(extension) = extensions.next() else {
+            return Ok(None);
+        };
+
+        let Some(offer) = offer else {
+            return Err(HandshakeError::InvalidExtension);
+        };
+
+        if extensions.next().is_some() {
+            return Err(HandshakeError::InvalidExtension);
+        }
+
+        match parse(extension, false) {
+            Ok(Some((params, _)))
+                if params.server_max_window_bits <= offer.server_max_window_bits
+                    && (params.server_no_context_takeover || !offer.server_no_context_takeover)
+                    && offer.can_inflate() =>
+            {
+                Ok(Some(DeflateParams {
+                    max_memory: offer.max_memory,
+                    ..params
+                }))
+            }
+            _ => Err(HandshakeError::InvalidExtension),
+        }
+    }
+}
+
+/// Parse single permessage-deflate extension with its parameters
+///
+/// Returns `Ok(None)` if extension has different name. Second tuple value
+/// indicates if `client_max_window_bits` parameter was present.
+fn parse(extension: &str, offer: bool) -> Result<Option<(DeflateParams, bool)>, ()> {
+    let mut params = extension.split(';').map(str::trim);
+    if params.next() != Some(EXTENSION_NAME) {
+        return Ok(None);
+    }
+
+    let mut result = DeflateParams::default();
+    let mut seen = [false; 4];
+    for param in params {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (param, None),
+        };
+
+        let index = match name {
+            "server_no_context_takeover" => 0,
+            "client_no_context_takeover" => 1,
+            "server_max_window_bits" => 2,
+            "client_max_window_bits" => 3,
+            _ => return Err(()),
+        };
+
+        if core::mem::replace(&mut seen[index], true) {
+            return Err(());
+        }
+
+        let window_bits = match value {
+            Some(value) => Some(
+                value
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|bits| (8..=15).contains(bits))
+                    .ok_or(())?,
+            ),
+            None => None,
+        };
+
+        match (index, window_bits) {
+            (0, None) => result.server_no_context_takeover = true,
+            (1, None) => result.client_no_context_takeover = true,
+            (2, Some(bits)) => result.server_max_window_bits = bits,
+            // value of client_max_window_bits is optional only in client offer
+            (3, Some(bits)) => result.client_max_window_bits = bits,
+            (3, None) if offer => {}
+            _ => return Err(()),
+        }
+    }
+
+    Ok(Some((result, seen[3])))
+}
+
+/// Decompressor of received messages (always uses 32KiB window, see
+/// `DeflateParams::INFLATE_MEMORY`)
+pub(crate) struct Inflater {
+    state: Box<InflateState>,
+
+    /// If dictionary should be reset after every message
+    no_context_takeover: bool,
+
+    /// Decompressed message
+    out: Vec<u8>,
+}
+
+impl Inflater {
+    /// Create decompressor for messages sent by peer
+    pub(crate) fn new(params: &DeflateParams, server: bool) -> Self {
+        let no_context_takeover = match server {
+            true => params.client_no_context_takeover,
+            false => params.server_no_context_takeover,
+        };
+
+        Self {
+            state: InflateState::new_boxed(DataFormat::Raw),
+            no_context_takeover,
+            out: Vec::new(),
+        }
+    }
+
+    /// Decompress whole message payload
+    ///
+    /// Fails with `MessageTooLarge` if decompressed data exceeds `max_len`
+    pub(crate) fn inflate(&mut self, data: &[u8], max_len: usize) -> Result<&[u8], WsError> {
+        self.out.clear();
+        for mut input in [data, &DEFLATE_TAIL[..]] {
+            loop {
+                let len = self.out.len();
+                self.out.resize(len + CHUNK_SIZE, 0);
+
+                let res = miniz_oxide::inflate::stream::inflate(
+                    &mut self.state,
+                    input,
+                    &mut self.out[len..],
+                    MZFlush::None,
+                );
+
+                self.out.truncate(len + res.bytes_written);
+                input = &input[res.bytes_consumed..];
+                if self.out.len() > max_len {
+                    return Err(WsError::MessageTooLarge);
+                }
+
+                match res.status {
+                    Ok(_) if input.is_empty() && res.bytes_written < CHUNK_SIZE => break,
+                    Ok(_) => {}
+                    Err(MZError::Buf) if input.is_empty() => break,
+                    Err(_) => return Err(WsError::InvalidCompressedData),
+                }
+            }
+        }
+
+        if self.no_context_takeover {
+            self.state.reset(DataFormat::Raw);
+        }
+
+        Ok(&self.out)
+    }
+
+    /// Last decompressed message
+    pub(crate) fn output(&self) -> &[u8] {
+        &self.out
+    }
+}
+
+/// Compressor of sent messages (see `DeflateParams::DEFLATE_MEMORY`)
+pub(crate) struct Deflater {
+    compressor: Box<CompressorOxide>,
+
+    /// If dictionary should be reset after every message
+    no_context_takeover: bool,
+
+    /// Compressed message (without trailing empty block)
+    out: Vec<u8>,
+}
+
+impl Deflater {
+    /// Create compressor for messages sent to peer
+    pub(crate) fn new(params: &DeflateParams, server: bool) -> Self {
+        let (no_context_takeover, window_bits) = match server {
+            true => (
+                params.server_no_context_takeover,
+                params.server_max_window_bits,
+            ),
+            false => (
+                params.client_no_context_takeover,
+                params.client_max_window_bits,
+            ),
+        };
+
+        // miniz_oxide always uses 32KiB window, so smaller window is honoured
+        // by limiting match distance to 1 (run-length encoding)
+        let strategy = match window_bits {
+            15 => CompressionStrategy::Default,
+            _ => CompressionStrategy::RLE,
+        };
+
+        let flags = create_comp_flags_from_zip_params(COMPRESSION_LEVEL, -15, strategy as i32);
+        Self {
+            compressor: Box::new(CompressorOxide::new(flags)),
+            no_context_takeover,
+            out: Vec::new(),
+        }
+    }
+
+    /// Compress whole message payload
+    pub(crate) fn deflate(&mut self, mut input: &[u8]) -> Result<&[u8], WsError> {
+        self.out.clear();
+        loop {
+            let len = self.out.len();
+            self.out.resize(len + CHUNK_SIZE, 0);
+
+            let res = miniz_oxide::deflate::stream::deflate(
+                &mut self.compressor,
+                input,
+                &mut self.out[len..],
+                MZFlush::Sync,
+            );
+
+            self.out.truncate(len + res.bytes_written);
+            input = &input[res.bytes_consumed..];
+            match res.status {
+                Ok(_) if input.is_empty() && res.bytes_written < CHUNK_SIZE => break,
+                Ok(_) => {}
+                Err(_) => return Err(WsError::InvalidCompressedData),
+            }
+        }
+
+        if self.out.ends_with(&DEFLATE_TAIL) {
+            self.out.truncate(self.out.len() - DEFLATE_TAIL.len());
+        }
+
+        if self.no_context_takeover {
+            self.compressor.reset();
+        }
+
+        Ok(&self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_params() {
+        let config = DeflateParams {
+            client_max_window_bits: 10,
+            ..Default::default()
+        };
+
+        // invalid offers are skipped
+        let offers = [
+            "x-webkit-deflate-frame",
+            "permessage-deflate; server_max_window_bits",
+            "permessage-deflate; server_no_context_takeover; server_no_context_takeover",
+            "permessage-deflate; client_max_window_bits; server_max_window_bits=\"12\"",
+        ];
+        let params = DeflateParams::negotiate(offers.into_iter(), &config).unwrap();
+        assert_eq!(
+            params,
+            DeflateParams {
+                server_max_window_bits: 12,
+                client_max_window_bits: 10,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            params.response(),
+            "permessage-deflate; server_max_window_bits=12; client_max_window_bits=10"
+        );
+
+        assert_eq!(
+            DeflateParams::default().offer().as_deref(),
+            Some("permessage-deflate; client_max_window_bits")
+        );
+        let offer = DeflateParams::default();
+        assert_eq!(
+            DeflateParams::from_response(
+                ["permessage-deflate; client_max_window_bits"].into_iter(),
+                Some(&offer)
+            ),
+            Err(HandshakeError::InvalidExtension)
+        );
+        assert_eq!(
+            DeflateParams::from_response(["permessage-deflate"].into_iter(), Some(&offer)),
+            Ok(Some(DeflateParams::default()))
+        );
+        assert_eq!(
+            DeflateParams::from_response(["permessage-deflate"].into_iter(), None),
+            Err(HandshakeError::InvalidExtension)
+        );
+
+        // server must keep limits requested by client
+        let offer = DeflateParams {
+            server_no_context_takeover: true,
+            server_max_window_bits: 10,
+            ..Default::default()
+        };
+        for response in [
+            "permessage-deflate; server_max_window_bits=10",
+            "permessage-deflate; server_no_context_takeover; server_max_window_bits=12",
+        ] {
+            assert_eq!(
+                DeflateParams::from_response([response].into_iter(), Some(&offer)),
+                Err(HandshakeError::InvalidExtension)
+            );
+        }
+    }
+
+    #[test]
+    fn memory_limit() {
+        let config = DeflateParams {
+            max_memory: DeflateParams::INFLATE_MEMORY - 1,
+            ..Default::default()
+        };
+        let offers = ["permessage-deflate"];
+        assert_eq!(DeflateParams::negotiate(offers.into_iter(), &config), None);
+        assert_eq!(config.offer(), None);
+
+        // limit of client offer is kept in negotiated parameters
+        let offer = DeflateParams {
+            max_memory: DeflateParams::INFLATE_MEMORY,
+            ..Default::default()
+        };
+        let params = DeflateParams::from_response(offers.into_iter(), Some(&offer)).unwrap();
+        assert_eq!(params, Some(offer));
+        assert!(!offer.can_deflate());
+        assert_eq!(
+            DeflateParams::from_response(offers.into_iter(), Some(&config)),
+            Err(HandshakeError::InvalidExtension)
+        );
+    }
+
+    /// Allocator counting bytes allocated by current thread
+    #[cfg(feature = "std")]
+    mod counter {
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        std::thread_local! {
+            pub static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+        }
+
+        struct Counter;
+
+        unsafe impl GlobalAlloc for Counter {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                _ = ALLOCATED.try_with(|n| n.set(n.get() + layout.size()));
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        #[global_allocator]
+        static GLOBAL: Counter = Counter;
+
+        /// Number of bytes allocated by `f`
+        pub fn allocated(f: impl FnOnce()) -> usize {
+            let start = ALLOCATED.with(Cell::get);
+            f();
+            ALLOCATED.with(Cell::get) - start
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn memory_constants() {
+        let params = DeflateParams::default();
+        let inflate = counter::allocated(|| drop(Inflater::new(&params, false)));
+        let deflate = counter::allocated(|| drop(Deflater::new(&params, false)));
+        assert!(inflate <= DeflateParams::INFLATE_MEMORY);
+        assert!(deflate <= DeflateParams::DEFLATE_MEMORY);
+    }
+
+    #[test]
+    fn inflate_rfc_sample() {
+        // "Hello" compressed twice with context takeover (RFC 7692 section 7.2.3.2)
+        let mut inflater = Inflater::new(&DeflateParams::default(), false);
+        let first = [0xF2, 0x48, 0xCD, 0xC9, 0xC9, 0x07, 0x00];
+        let second = [0xF2, 0x00, 0x11, 0x00, 0x00];
+        assert_eq!(inflater.inflate(&first, 64), Ok(&b"Hello"[..]));
+        assert_eq!(inflater.inflate(&second, 64), Ok(&b"Hello"[..]));
+
+        assert_eq!(inflater.inflate(&first, 4), Err(WsError::MessageTooLarge));
+
+        // reserved block type
+        let mut inflater = Inflater::new(&DeflateParams::default(), false);
+        assert_eq!(
+            inflater.inflate(&[0xFF, 0xFF], 64),
+            Err(WsError::InvalidCompressedData)
+        );
+    }
+
+    #[test]
+    fn deflate_roundtrip() {
+        let params = DeflateParams {
+            server_no_context_takeover: true,
+            server_max_window_bits: 9,
+            ..Default::default()
+        };
+
+        let data: Vec<u8> = (0..5000).map(|i| (i / 100) as u8).collect();
+        let mut deflater = Deflater::new(&params, true);
+        let mut inflater = Inflater::new(&params, false);
+        for _ in 0..3 {
+            let compressed = deflater.deflate(&data).unwrap();
+            assert!(compressed.len() < data.len() / 5);
+            assert_eq!(inflater.inflate(compressed, data.len()), Ok(&data[..]));
+        }
+
+        let mut deflater = Deflater::new(&DeflateParams::default(), false);
+        let compressed = deflater.deflate(&[]).unwrap().to_vec();
+        assert_eq!(inflater.inflate(&compressed, 0), Ok(&[][..]));
+    }
+}
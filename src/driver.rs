@@ -0,0 +1,238 @@
+use crate::{
+    framer::ParsedFrame, AcceptOptions, ConnectionError, WsError, WsFrame, WsRxFramer, WsTxFramer,
+    WsUrl,
+};
+
+/// Sans-IO handshake and receive logic shared by transport wrappers
+/// (`WsConnection`, `WsStream` and `WsEmbeddedStream`)
+///
+/// Wraps `WsRxFramer` and `WsTxFramer` that use caller-provided buffers.
+/// Fragmented messages are reassembled, so `rx_buf` must be large enough to
+/// hold whole message. Wrappers only move data between transport and buffers
+/// returned by driver.
+pub(crate) struct WsDriver<'a> {
+    rx: WsRxFramer<'a>,
+    tx: WsTxFramer<'a>,
+
+    /// Number of bytes read into `read_buf` that weren't processed yet
+    received: usize,
+}
+
+impl<'a> WsDriver<'a> {
+    pub(crate) fn client(rx_buf: &'a mut [u8], tx_buf: &'a mut [u8]) -> Self {
+        Self {
+            rx: WsRxFramer::new_client(rx_buf).with_reassembly(true),
+            tx: WsTxFramer::new(true, tx_buf),
+            received: 0,
+        }
+    }
+
+    pub(crate) fn server(rx_buf: &'a mut [u8], tx_buf: &'a mut [u8]) -> Self {
+        Self {
+            rx: WsRxFramer::new_server(rx_buf).with_reassembly(true),
+            tx: WsTxFramer::new(false, tx_buf),
+            received: 0,
+        }
+    }
+
+    /// Upgrade request that should be sent to server
+    pub(crate) fn upgrade_request(&mut self, url: &WsUrl<'_>) -> Result<&[u8], WsError> {
+        self.tx.generate_http_upgrade(url.host, url.path, &[], None)
+    }
+
+    /// Process received part of server handshake response, returns `true`
+    /// once whole valid response is received
+    pub(crate) fn handshake_response(&mut self) -> Result<bool, WsError> {
+        let key = self.tx.sec_websocket_key().unwrap_or_default();
+        let n = core::mem::take(&mut self.received);
+        let res = self.rx.process_handshake_response(n, key, &[])?;
+        Ok(res.is_some())
+    }
+
+    /// Process received part of client upgrade request
+    ///
+    /// Once whole request is received, returns response that should be sent
+    /// to client and result of handshake (rejected request is answered with
+    /// error response).
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn accept<'b>(
+        &'b mut self,
+        options: &AcceptOptions<'b>,
+    ) -> Result<Option<(&'b [u8], Result<(), WsError>)>, WsError> {
+        let n = core::mem::take(&mut self.received);
+        let Some(req) = self.rx.process_http_request(n)? else {
+            return Ok(None);
+        };
+
+        let res = self.tx.generate_handshake_accept(&req, options)?;
+        let result = match res.error {
+            Some(err) => Err(WsError::Handshake(err)),
+            None => Ok(()),
+        };
+
+        Ok(Some((res.data, result)))
+    }
+
+    /// Parse next received frame (or reassembled message)
+    ///
+    /// Returned value should be passed to `parsed_frame`
+    pub(crate) fn next_frame(&mut self) -> Result<Option<ParsedFrame>, WsError> {
+        self.rx
+            .revolve_write_offset(core::mem::take(&mut self.received));
+        self.rx.next_frame()
+    }
+
+    pub(crate) fn parsed_frame(&mut self, parsed: ParsedFrame) -> Result<WsFrame<'_>, WsError> {
+        self.rx.parsed_frame(parsed)
+    }
+
+    /// Packet of frame that should be sent
+    pub(crate) fn frame(&mut self, frame: WsFrame<'_>) -> Result<&[u8], WsError> {
+        self.tx.frame(frame)
+    }
+
+    /// Free space of rx buffer that transport should read into
+    ///
+    /// Fails if buffer is full (frame or message won't fit into it)
+    pub(crate) fn read_buf(&mut self) -> Result<&mut [u8], WsError> {
+        let buf = &mut self.rx.mut_buf()[self.received..];
+        match buf.is_empty() {
+            true => Err(WsError::FrameTooLarge),
+            false => Ok(buf),
+        }
+    }
+
+    /// Record number of bytes read into `read_buf` (0 if transport was closed)
+    pub(crate) fn received<E>(&mut self, n: usize) -> Result<(), ConnectionError<E>> {
+        match n {
+            0 => Err(ConnectionError::Closed),
+            n => {
+                self.received += n;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_buf_full_and_closed() {
+        let mut rx_buf = [0; 4];
+        let mut tx_buf = [0; 16];
+        let mut driver = WsDriver::server(&mut rx_buf, &mut tx_buf);
+
+        assert_eq!(driver.received::<()>(0), Err(ConnectionError::Closed));
+        driver.read_buf().unwrap()[..4].copy_from_slice(b"\x81\x80\0\0");
+        driver.received::<()>(4).unwrap();
+        assert_eq!(driver.read_buf().err(), Some(WsError::FrameTooLarge));
+        assert!(driver.next_frame().unwrap().is_none());
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    /// Transport that reads from static input and stores written data
+    pub(crate) struct MockIo<'a> {
+        input: &'a [u8],
+
+        /// Maximum number of bytes returned by single read
+        chunk: usize,
+
+        output: [u8; 512],
+        output_len: usize,
+    }
+
+    impl MockIo<'_> {
+        pub(crate) fn new(input: &[u8], chunk: usize) -> MockIo<'_> {
+            MockIo {
+                input,
+                chunk,
+                output: [0; 512],
+                output_len: 0,
+            }
+        }
+
+        pub(crate) fn read(&mut self, buf: &mut [u8]) -> usize {
+            let n = buf.len().min(self.input.len()).min(self.chunk);
+            buf[..n].copy_from_slice(&self.input[..n]);
+            self.input = &self.input[n..];
+            n
+        }
+
+        pub(crate) fn write(&mut self, buf: &[u8]) -> usize {
+            self.output[self.output_len..self.output_len + buf.len()].copy_from_slice(buf);
+            self.output_len += buf.len();
+            buf.len()
+        }
+
+        /// Check that handshake response was sent, followed by `tail`
+        pub(crate) fn check_output(&self, tail: &[u8]) {
+            let output = &self.output[..self.output_len];
+            assert!(output.starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"));
+            assert!(output.ends_with(tail));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Read for MockIo<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            Ok(MockIo::read(self, buf))
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Write for MockIo<'_> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(MockIo::write(self, buf))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "embedded-io")]
+    impl embedded_io::ErrorType for MockIo<'_> {
+        type Error = core::convert::Infallible;
+    }
+
+    #[cfg(all(feature = "embedded-io-async", not(feature = "embedded-io")))]
+    impl embedded_io_async::ErrorType for MockIo<'_> {
+        type Error = core::convert::Infallible;
+    }
+
+    #[cfg(feature = "embedded-io")]
+    impl embedded_io::Read for MockIo<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Ok(MockIo::read(self, buf))
+        }
+    }
+
+    #[cfg(feature = "embedded-io")]
+    impl embedded_io::Write for MockIo<'_> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            Ok(MockIo::write(self, buf))
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "embedded-io-async")]
+    impl embedded_io_async::Read for MockIo<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Ok(MockIo::read(self, buf))
+        }
+    }
+
+    #[cfg(feature = "embedded-io-async")]
+    impl embedded_io_async::Write for MockIo<'_> {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            Ok(MockIo::write(self, buf))
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::CloseCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Error returned when received data isn't a valid websocket stream
+pub enum WsError {
+    /// Frame uses opcode reserved for further use (3-7 and 11-15)
+    ReservedOpcode(u8),
+
+    /// Payload length wasn't encoded using minimal number of bytes
+    NonMinimalLength,
+
+    /// 64-bit payload length has most significant bit set
+    InvalidLength,
+
+    /// Frame won't fit into framer buffer (or exceeds configured limit)
+    FrameTooLarge,
+
+    /// Reassembled message won't fit into framer buffer (or exceeds configured limit)
+    MessageTooLarge,
+
+    /// Server received frame that isn't masked
+    UnmaskedFrame,
+
+    /// Client received masked frame
+    MaskedFrame,
+
+    /// Control frame has FIN bit cleared (control frames can't be fragmented)
+    FragmentedControlFrame,
+
+    /// Control frame payload is longer than allowed (at most 125 bytes)
+    ControlFrameTooLarge,
+
+    /// Continuation frame received without fragmented message in progress
+    UnexpectedContinuation,
+
+    /// New data frame received before previous fragmented message was finished
+    ExpectedContinuation,
+
+    /// Close frame payload is 1 byte long (status code is truncated)
+    InvalidClosePayload,
+
+    /// Close status code is reserved or not allowed on the wire
+    InvalidCloseCode(u16),
+
+    /// Text frame or close reason isn't valid utf-8
+    InvalidUtf8,
+
+    /// Frame has RSV bit set that has no meaning for it
+    UnexpectedRsv,
+
+    /// Compressed message payload cannot be (de)compressed
+    InvalidCompressedData,
+
+    /// Negotiated extension failed to encode or decode payload
+    ExtensionFailed,
+
+    /// Frame type cannot be sent (`WsFrame::Unknown` or `WsFrame::Fragment`)
+    UnsupportedFrame,
+
+    /// Frame payload must be masked (in place) before it's sent
+    MaskingRequired,
+
+    /// Frame payload can't bypass negotiated extensions (or permessage-deflate)
+    ExtensionsNegotiated,
+
+    /// Streamed frame payload doesn't match length passed to `begin_frame`,
+    /// other frame was generated before it was finished, or `partial_frame`
+    /// offset is past end of payload
+    InvalidStreamedFrame,
+
+    /// Frame cannot be sent after close frame was sent
+    ConnectionClosed,
+
+    /// Peer didn't respond to keepalive ping in time
+    PeerTimeout,
+
+    /// Framer buffer is too small to hold generated data
+    BufferTooSmall { needed: usize },
+
+    /// Received http message is malformed
+    InvalidHttp,
+
+    /// Received http message won't fit into framer buffer
+    HttpTooLarge,
+
+    /// Websocket handshake failed
+    Handshake(HandshakeError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Reason why websocket handshake failed
+pub enum HandshakeError {
+    /// Upgrade request method isn't GET
+    InvalidMethod,
+
+    /// Upgrade request uses HTTP version older than HTTP/1.1
+    InvalidHttpVersion,
+
+    /// Missing or invalid Upgrade header
+    InvalidUpgrade,
+
+    /// Missing or invalid Connection header
+    InvalidConnection,
+
+    /// Missing or malformed Sec-WebSocket-Key header
+    InvalidKey,
+
+    /// Sec-WebSocket-Version other than 13
+    UnsupportedVersion,
+
+    /// Server responded with status code other than 101
+    InvalidStatus(u16),
+
+    /// Sec-WebSocket-Accept doesn't match key sent by client
+    InvalidAccept,
+
+    /// Server selected subprotocol that wasn't offered by client
+    InvalidProtocol,
+
+    /// Server accepted unknown extension or sent invalid extension parameters
+    InvalidExtension,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Error returned by connection wrappers over transport with error type `E`
+pub enum ConnectionError<E> {
+    /// Transport read or write failed
+    Io(E),
+
+    /// Received data isn't valid websocket stream (or handshake failed)
+    Ws(WsError),
+
+    /// Transport was closed by peer
+    Closed,
+}
+
+impl WsError {
+    /// Close code that should be sent to the peer before closing connection
+    pub fn close_code(&self) -> CloseCode {
+        match self {
+            WsError::ReservedOpcode(_)
+            | WsError::NonMinimalLength
+            | WsError::InvalidLength
+            | WsError::InvalidClosePayload
+            | WsError::InvalidCloseCode(_)
+            | WsError::UnmaskedFrame
+            | WsError::MaskedFrame
+            | WsError::FragmentedControlFrame
+            | WsError::ControlFrameTooLarge
+            | WsError::UnexpectedContinuation
+            | WsError::ExpectedContinuation
+            | WsError::UnexpectedRsv
+            | WsError::InvalidCompressedData
+            | WsError::ExtensionFailed
+            | WsError::InvalidHttp
+            | WsError::Handshake(_) => CloseCode::ProtocolError,
+            WsError::InvalidUtf8 => CloseCode::InvalidPayload,
+            WsError::FrameTooLarge | WsError::MessageTooLarge | WsError::HttpTooLarge => {
+                CloseCode::MessageTooBig
+            }
+            WsError::PeerTimeout => CloseCode::GoingAway,
+            WsError::UnsupportedFrame
+            | WsError::MaskingRequired
+            | WsError::ExtensionsNegotiated
+            | WsError::InvalidStreamedFrame
+            | WsError::ConnectionClosed
+            | WsError::BufferTooSmall { .. } => CloseCode::InternalError,
+        }
+    }
+}
+
+impl core::fmt::Display for WsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WsError::ReservedOpcode(opcode) => write!(f, "reserved opcode: {opcode}"),
+            WsError::NonMinimalLength => write!(f, "non-minimal payload length encoding"),
+            WsError::InvalidLength => write!(f, "payload length with most significant bit set"),
+            WsError::FrameTooLarge => write!(f, "frame larger than framer buffer or limit"),
+            WsError::MessageTooLarge => write!(f, "message larger than framer buffer or limit"),
+            WsError::UnmaskedFrame => write!(f, "received unmasked frame from client"),
+            WsError::MaskedFrame => write!(f, "received masked frame from server"),
+            WsError::FragmentedControlFrame => write!(f, "fragmented control frame"),
+            WsError::ControlFrameTooLarge => write!(f, "control frame payload too long"),
+            WsError::UnexpectedContinuation => write!(f, "unexpected continuation frame"),
+            WsError::ExpectedContinuation => write!(f, "expected continuation frame"),
+            WsError::InvalidClosePayload => write!(f, "invalid close frame payload"),
+            WsError::InvalidCloseCode(code) => write!(f, "invalid close code: {code}"),
+            WsError::InvalidUtf8 => write!(f, "invalid utf-8 in text payload"),
+            WsError::UnexpectedRsv => write!(f, "unexpected rsv bit set"),
+            WsError::InvalidCompressedData => write!(f, "invalid compressed payload"),
+            WsError::ExtensionFailed => write!(f, "extension failed to process payload"),
+            WsError::UnsupportedFrame => write!(f, "unsupported frame type"),
+            WsError::InvalidStreamedFrame => write!(f, "streamed frame payload length mismatch"),
+            WsError::MaskingRequired => write!(f, "payload must be masked in place"),
+            WsError::ExtensionsNegotiated => {
+                write!(f, "payload must be processed by negotiated extensions")
+            }
+            WsError::ConnectionClosed => write!(f, "connection is closing or closed"),
+            WsError::PeerTimeout => write!(f, "peer didn't respond to ping"),
+            WsError::BufferTooSmall { needed } => {
+                write!(f, "buffer too small ({needed} bytes needed)")
+            }
+            WsError::InvalidHttp => write!(f, "malformed http message"),
+            WsError::HttpTooLarge => write!(f, "http message larger than framer buffer"),
+            WsError::Handshake(err) => write!(f, "handshake failed: {err}"),
+        }
+    }
+}
+
+impl core::error::Error for WsError {}
+
+impl core::fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HandshakeError::InvalidMethod => write!(f, "invalid request method"),
+            HandshakeError::InvalidHttpVersion => write!(f, "unsupported http version"),
+            HandshakeError::InvalidUpgrade => write!(f, "missing or invalid Upgrade header"),
+            HandshakeError::InvalidConnection => write!(f, "missing or invalid Connection header"),
+            HandshakeError::InvalidKey => write!(f, "missing or invalid Sec-WebSocket-Key"),
+            HandshakeError::UnsupportedVersion => write!(f, "unsupported websocket version"),
+            HandshakeError::InvalidStatus(code) => write!(f, "unexpected status code: {code}"),
+            HandshakeError::InvalidAccept => write!(f, "invalid Sec-WebSocket-Accept"),
+            HandshakeError::InvalidProtocol => write!(f, "invalid Sec-WebSocket-Protocol"),
+            HandshakeError::InvalidExtension => write!(f, "invalid Sec-WebSocket-Extensions"),
+        }
+    }
+}
+
+impl core::error::Error for HandshakeError {}
+
+impl<E> From<WsError> for ConnectionError<E> {
+    fn from(err: WsError) -> Self {
+        ConnectionError::Ws(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for ConnectionError<std::io::Error> {
+    fn from(err: std::io::Error) -> Self {
+        ConnectionError::Io(err)
+    }
+}
+
+impl<E: core::fmt::Debug> core::fmt::Display for ConnectionError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConnectionError::Io(err) => write!(f, "transport error: {err:?}"),
+            ConnectionError::Ws(err) => write!(f, "{err}"),
+            ConnectionError::Closed => write!(f, "connection closed"),
+        }
+    }
+}
+
+impl<E: core::fmt::Debug> core::error::Error for ConnectionError<E> {}
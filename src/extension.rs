@@ -0,0 +1,384 @@
+use crate::{HandshakeError, WsError, WsFrameHeader};
+
+/// Websocket extension that transforms frame payloads (RFC 6455 section 9)
+///
+/// Extensions are negotiated during handshake using Sec-WebSocket-Extensions
+/// header. `WsRxFramer` and `WsTxFramer` hold their own chain of extensions
+/// (see `with_extensions`), so one instance is needed for each direction.
+///
+/// Every negotiated extension is applied to every frame (including control
+/// frames), outgoing payload is encoded in chain order and received payload
+/// is decoded in reverse order.
+pub trait Extension {
+    /// Extension token used in Sec-WebSocket-Extensions header
+    fn name(&self) -> &str;
+
+    /// Parameters sent in client offer (`;` separated, without extension name)
+    fn offer(&self) -> &str {
+        ""
+    }
+
+    /// Accept parameters offered by client and configure extension (server side)
+    ///
+    /// Returns `false` if offer is declined
+    fn accept(&mut self, params: &str) -> bool;
+
+    /// Parameters sent in server response after offer was accepted
+    fn response(&self) -> &str {
+        ""
+    }
+
+    /// Configure extension using parameters from server response
+    fn configure(&mut self, params: &str) -> Result<(), HandshakeError>;
+
+    /// RSV bits used by extension (`0b100` - RSV1, `0b010` - RSV2, `0b001` - RSV3)
+    ///
+    /// Received frames with RSV bits that aren't claimed by any negotiated
+    /// extension are rejected.
+    fn rsv_bits(&self) -> u8 {
+        0
+    }
+
+    /// Encode outgoing payload in place
+    ///
+    /// Payload is stored at the start of `buf` and is `len` bytes long, rest
+    /// of `buf` can be used to grow payload. Extension can set RSV bits of
+    /// frame header. Returns new payload length.
+    fn encode(
+        &mut self,
+        header: &mut WsFrameHeader,
+        buf: &mut [u8],
+        len: usize,
+    ) -> Result<usize, WsError>;
+
+    /// Decode received (unmasked) payload in place
+    ///
+    /// Returns new payload length (cannot be larger than `payload` length)
+    fn decode(&mut self, header: &WsFrameHeader, payload: &mut [u8]) -> Result<usize, WsError>;
+}
+
+/// Split extension into its name and parameters
+fn split_extension(extension: &str) -> (&str, &str) {
+    match extension.split_once(';') {
+        Some((name, params)) => (name.trim(), params.trim()),
+        None => (extension.trim(), ""),
+    }
+}
+
+/// If extension is handled by framer itself (not by extension chain)
+fn is_builtin(name: &str) -> bool {
+    cfg!(feature = "deflate") && name == "permessage-deflate"
+}
+
+#[cfg(feature = "http")]
+/// Accept client offers using extensions from chain (server side)
+///
+/// Accepted extensions are moved to the front of the chain, returns their count
+pub(crate) fn accept<'e>(
+    chain: &mut [&mut dyn Extension],
+    offers: impl Iterator<Item = &'e str>,
+) -> usize {
+    let mut negotiated = 0;
+    for offer in offers {
+        let (name, params) = split_extension(offer);
+        let Some(i) = chain[negotiated..]
+            .iter()
+            .position(|extension| extension.name() == name)
+        else {
+            continue;
+        };
+
+        if chain[negotiated + i].accept(params) {
+            chain.swap(negotiated, negotiated + i);
+            negotiated += 1;
+        }
+    }
+
+    negotiated
+}
+
+/// Configure extensions from chain using extensions accepted by server
+///
+/// Configured extensions are moved to the front of the chain (in accepted
+/// order), returns their count. Fails if extension isn't in chain.
+pub(crate) fn configure<'e>(
+    chain: &mut [&mut dyn Extension],
+    accepted: impl Iterator<Item = &'e str>,
+) -> Result<usize, HandshakeError> {
+    let mut negotiated = 0;
+    for extension in accepted {
+        let (name, params) = split_extension(extension);
+        if is_builtin(name) {
+            continue;
+        }
+
+        let i = chain[negotiated..]
+            .iter()
+            .position(|extension| extension.name() == name)
+            .ok_or(HandshakeError::InvalidExtension)?;
+
+        chain[negotiated + i].configure(params)?;
+        chain.swap(negotiated, negotiated + i);
+        negotiated += 1;
+    }
+
+    Ok(negotiated)
+}
+
+/// RSV bits claimed by extensions
+pub(crate) fn rsv_bits(chain: &[&mut dyn Extension]) -> u8 {
+    chain
+        .iter()
+        .fold(0, |bits, extension| bits | extension.rsv_bits())
+}
+
+#[cfg(feature = "http")]
+/// Length of Sec-WebSocket-Extensions header value listing extensions
+/// with their offer (or response) parameters
+pub(crate) fn header_value_len(chain: &[&mut dyn Extension], offer: bool) -> usize {
+    chain
+        .iter()
+        .map(|extension| {
+            let params = match offer {
+                true => extension.offer(),
+                false => extension.response(),
+            };
+
+            extension.name().len()
+                + if params.is_empty() {
+                    0
+                } else {
+                    2 + params.len()
+                }
+        })
+        .sum::<usize>()
+        + chain.len().saturating_sub(1) * 2
+}
+
+#[cfg(feature = "http")]
+/// Write Sec-WebSocket-Extensions header value into buffer
+///
+/// Caller must ensure that buffer is large enough (see `header_value_len`)
+pub(crate) fn write_header_value(
+    buf: &mut [u8],
+    offset: &mut usize,
+    chain: &[&mut dyn Extension],
+    offer: bool,
+) {
+    for (i, extension) in chain.iter().enumerate() {
+        let params = match offer {
+            true => extension.offer(),
+            false => extension.response(),
+        };
+
+        let parts: [&[u8]; 4] = [
+            if i == 0 { b"" } else { b", " },
+            extension.name().as_bytes(),
+            if params.is_empty() { b"" } else { b"; " },
+            params.as_bytes(),
+        ];
+
+        for part in parts {
+            buf[*offset..*offset + part.len()].copy_from_slice(part);
+            *offset += part.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{WsFrame, WsRxFramer, WsTxFramer};
+
+    /// Appends xor checksum byte to payload of every frame (RSV2)
+    #[derive(Default)]
+    struct Checksum {
+        seed: u8,
+    }
+
+    impl Extension for Checksum {
+        fn name(&self) -> &str {
+            "x-checksum"
+        }
+
+        fn offer(&self) -> &str {
+            "seed=7"
+        }
+
+        fn accept(&mut self, params: &str) -> bool {
+            self.configure(params).is_ok()
+        }
+
+        fn response(&self) -> &str {
+            "seed=7"
+        }
+
+        fn configure(&mut self, params: &str) -> Result<(), HandshakeError> {
+            self.seed = params
+                .strip_prefix("seed=")
+                .and_then(|seed| seed.parse().ok())
+                .ok_or(HandshakeError::InvalidExtension)?;
+
+            Ok(())
+        }
+
+        fn rsv_bits(&self) -> u8 {
+            0b010
+        }
+
+        fn encode(
+            &mut self,
+            header: &mut WsFrameHeader,
+            buf: &mut [u8],
+            len: usize,
+        ) -> Result<usize, WsError> {
+            if len == buf.len() {
+                return Err(WsError::BufferTooSmall { needed: len + 1 });
+            }
+
+            buf[len] = buf[..len].iter().fold(self.seed, |sum, x| sum ^ x);
+            header.rsv2 = true;
+            Ok(len + 1)
+        }
+
+        fn decode(&mut self, header: &WsFrameHeader, payload: &mut [u8]) -> Result<usize, WsError> {
+            if !header.rsv2 {
+                return Ok(payload.len());
+            }
+
+            let Some((sum, data)) = payload.split_last() else {
+                return Err(WsError::ExtensionFailed);
+            };
+
+            match data.iter().fold(self.seed, |sum, x| sum ^ x) == *sum {
+                true => Ok(data.len()),
+                false => Err(WsError::ExtensionFailed),
+            }
+        }
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn negotiate_extensions() {
+        let mut checksum = Checksum::default();
+        let mut other = Checksum::default();
+        let mut chain: [&mut dyn Extension; 2] = [&mut other, &mut checksum];
+        assert_eq!(header_value_len(&chain, true), 38);
+
+        let offers = ["x-unknown", "x-checksum; seed=x", "x-checksum; seed=3"];
+        assert_eq!(accept(&mut chain, offers.into_iter()), 1);
+        assert_eq!(rsv_bits(&chain[..1]), 0b010);
+
+        let accepted = ["x-checksum; seed=7", "x-unknown"];
+        assert_eq!(
+            configure(&mut chain, accepted.into_iter()),
+            Err(HandshakeError::InvalidExtension)
+        );
+    }
+
+    #[test]
+    fn extension_roundtrip() {
+        let mut tx_checksum = Checksum::default();
+        let mut tx_chain: [&mut dyn Extension; 1] = [&mut tx_checksum];
+        let mut tx_buf = [0; 256];
+        let mut tx = WsTxFramer::new(true, &mut tx_buf).with_extensions(&mut tx_chain);
+        tx.negotiate_extensions(["x-checksum; seed=7"]).unwrap();
+
+        let mut rx_checksum = Checksum::default();
+        let mut rx_chain: [&mut dyn Extension; 1] = [&mut rx_checksum];
+        let mut rx_buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut rx_buf)
+            .with_reassembly(true)
+            .with_extensions(&mut rx_chain);
+        rx.negotiate_extensions(["x-checksum; seed=7"]).unwrap();
+
+        let data = tx.text("hello").unwrap();
+        assert_eq!(data[0], 0xA1);
+        assert_eq!(data[1] & 0x7F, 6);
+        rx.mut_buf()[..data.len()].copy_from_slice(data);
+        rx.revolve_write_offset(data.len());
+
+        let data = tx.ping(b"p").unwrap();
+        rx.mut_buf()[..data.len()].copy_from_slice(data);
+        rx.revolve_write_offset(data.len());
+
+        assert_eq!(rx.process_data(), Ok(Some(WsFrame::Text("hello"))));
+        assert_eq!(rx.process_data(), Ok(Some(WsFrame::Ping(b"p"))));
+        assert_eq!(
+            rx.process_chunk().map(|_| ()),
+            Err(WsError::ExtensionsNegotiated)
+        );
+
+        // encoded control frame payload can't exceed 125 bytes
+        assert_eq!(tx.ping(&[0; 125]), Err(WsError::ControlFrameTooLarge));
+        assert_eq!(
+            tx.header_in_place(2, &mut [0]).map(|_| ()),
+            Err(WsError::ExtensionsNegotiated)
+        );
+
+        // corrupted checksum and RSV bit not claimed by any extension
+        rx.mut_buf()[..4].copy_from_slice(&[0xA2, 0x02, 0x01, 0x00]);
+        rx.revolve_write_offset(4);
+        assert_eq!(rx.process_data(), Err(WsError::ExtensionFailed));
+
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf);
+        rx.mut_buf()[..3].copy_from_slice(&[0x92, 0x01, 0x00]);
+        rx.revolve_write_offset(3);
+        assert_eq!(rx.process_data(), Err(WsError::UnexpectedRsv));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn extension_handshake() {
+        use crate::AcceptOptions;
+
+        let mut client_checksum = Checksum::default();
+        let mut client_chain: [&mut dyn Extension; 1] = [&mut client_checksum];
+        let mut client_tx_buf = [0; 512];
+        let mut client_tx =
+            WsTxFramer::new(true, &mut client_tx_buf).with_extensions(&mut client_chain);
+        let request = client_tx
+            .generate_http_upgrade("localhost", "/", &[], None)
+            .unwrap();
+        assert!(core::str::from_utf8(request)
+            .unwrap()
+            .contains("\r\nSec-WebSocket-Extensions: x-checksum; seed=7\r\n"));
+
+        let mut server_rx_buf = [0; 512];
+        let mut server_rx = WsRxFramer::new(&mut server_rx_buf);
+        server_rx.mut_buf()[..request.len()].copy_from_slice(request);
+        let req = server_rx
+            .process_http_request(request.len())
+            .unwrap()
+            .unwrap();
+
+        let mut server_checksum = Checksum::default();
+        let mut server_chain: [&mut dyn Extension; 1] = [&mut server_checksum];
+        let mut server_tx_buf = [0; 512];
+        let mut server_tx =
+            WsTxFramer::new(false, &mut server_tx_buf).with_extensions(&mut server_chain);
+        // extensions header is found regardless of additional headers
+        let headers = [httparse::Header {
+            name: "Server",
+            value: b"ws-framer",
+        }];
+        let options = AcceptOptions {
+            headers: &headers,
+            ..Default::default()
+        };
+        let res = server_tx.generate_handshake_accept(&req, &options).unwrap();
+        assert!(res.extensions().eq(["x-checksum; seed=7"]));
+
+        let key = client_tx.sec_websocket_key().unwrap();
+        let mut client_rx_buf = [0; 512];
+        let mut client_rx = WsRxFramer::new(&mut client_rx_buf);
+        client_rx.mut_buf()[..res.data.len()].copy_from_slice(res.data);
+        let resp = client_rx
+            .process_handshake_response(res.data.len(), key, &[])
+            .unwrap()
+            .unwrap();
+        assert!(resp.extensions().eq(["x-checksum; seed=7"]));
+        assert_eq!(client_tx.negotiate_extensions(resp.extensions()), Ok(()));
+    }
+}
@@ -1,4 +1,18 @@
-use crate::{crypto::Base64Pad, WsFrame, WsFrameHeader};
+#[cfg(feature = "http")]
+use crate::crypto::Base64Pad;
+#[cfg(feature = "http")]
+use crate::handshake::{AcceptOptions, HandshakeResponse};
+#[cfg(feature = "deflate")]
+use crate::{
+    deflate::{Deflater, Inflater},
+    DeflateParams,
+};
+use crate::{
+    extension, CloseCode, Extension, HandshakeError, RxRules, Utf8Validator, WsError, WsFrame,
+    WsFrameHeader,
+};
+#[cfg(feature = "http")]
+use core::ops::Range;
 #[cfg(feature = "http")]
 use httparse::Header;
 
@@ -13,11 +27,103 @@ pub struct WsRxFramer<'a> {
     /// Offset in internal buffer (for writing responses from server)
     write_offset: usize,
 
+    /// Calculated current frame packet start offset
+    current_packet_start: usize,
+
     /// Calculated current frame packet end offset
     current_packet_end: usize,
 
     /// If old frame should be disposed from internal buffer in next call
     shift: bool,
+
+    /// If fragmented messages should be coalesced into single frame
+    reassemble: bool,
+
+    /// Opcode of fragmented message that is currently reassembled
+    message_opcode: Option<u8>,
+
+    /// Length of already reassembled message payload (stored at buffer start)
+    message_len: usize,
+
+    /// Validator for fragments of reassembled text message
+    utf8: Utf8Validator,
+
+    /// Number of payload bytes of current frame already returned as chunks
+    chunk_offset: usize,
+
+    /// If fragmented message that is currently reassembled is compressed
+    message_compressed: bool,
+
+    /// Decompressor of negotiated permessage-deflate extension
+    #[cfg(feature = "deflate")]
+    inflater: Option<Inflater>,
+
+    /// Permessage-deflate parameters offered in client upgrade request
+    #[cfg(feature = "deflate")]
+    deflate_offer: Option<DeflateParams>,
+
+    /// Extension chain (negotiated extensions are stored at the front)
+    extensions: &'a mut [&'a mut dyn Extension],
+
+    /// Number of negotiated extensions
+    negotiated_extensions: usize,
+
+    /// Maximum payload length of reassembled (or decompressed) message
+    max_message_len: usize,
+
+    /// Validation rules of received headers (strict mode, masking and limits)
+    rules: RxRules,
+}
+
+/// Frame parsed by `WsRxFramer::next_frame` (payload is stored inside framer)
+pub(crate) enum ParsedFrame {
+    /// Single frame stored at current packet offset (not validated yet)
+    Frame(WsFrameHeader),
+
+    /// Reassembled message with given opcode stored at buffer start
+    Message(u8),
+
+    /// Decompressed message with given opcode stored in inflater
+    #[cfg(feature = "deflate")]
+    Inflated(u8),
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Encoded frame header (2-14 bytes) returned by `WsTxFramer::header_vectored`
+/// and `WsTxFramer::header_in_place`
+pub struct WsHeaderBuf {
+    data: [u8; 14],
+    len: usize,
+}
+
+impl core::ops::Deref for WsHeaderBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+impl AsRef<[u8]> for WsHeaderBuf {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Part of frame payload returned by `WsRxFramer::process_chunk`
+pub struct PayloadChunk<'a> {
+    /// Header of frame that chunk belongs to
+    pub header: WsFrameHeader,
+
+    /// Unmasked chunk data
+    pub data: &'a [u8],
+
+    /// Offset of chunk data inside frame payload
+    pub offset: usize,
+
+    /// If this is the last chunk of frame payload
+    pub is_last: bool,
 }
 
 #[cfg(feature = "alloc")]
@@ -27,390 +133,1368 @@ pub struct HttpHeaderOwned {
 }
 
 #[cfg(feature = "http")]
-pub struct HttpResponse {
+/// Parsed http response (received by client)
+/// Stores references to inner framer buffer
+pub struct HttpResponse<'a> {
     pub status_code: u16,
 
+    /// Subprotocol selected by server (one of protocols offered by client)
+    pub protocol: Option<&'a str>,
+
+    /// Negotiated permessage-deflate parameters (if accepted by server)
+    #[cfg(feature = "deflate")]
+    pub deflate: Option<DeflateParams>,
+
     #[cfg(feature = "alloc")]
     pub headers: alloc::vec::Vec<HttpHeaderOwned>,
+
+    raw_headers: [Header<'a>; crate::consts::HTTP_MAX_HEADERS],
+    raw_headers_len: usize,
+}
+
+#[cfg(feature = "http")]
+impl<'a> HttpResponse<'a> {
+    /// Extensions accepted by server with their parameters (Sec-WebSocket-Extensions)
+    ///
+    /// Should be passed to `negotiate_extensions` of both framers
+    pub fn extensions(&self) -> impl Iterator<Item = &'a str> + '_ {
+        crate::handshake::header_tokens(
+            &self.raw_headers[..self.raw_headers_len],
+            "Sec-WebSocket-Extensions",
+        )
+    }
+}
+
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+/// Parsed http upgrade request (received by server)
+/// Stores references to inner framer buffer
+pub struct HttpRequest<'a> {
+    pub method: &'a str,
+    pub path: &'a str,
+
+    /// Http minor version (1 for HTTP/1.1)
+    pub version: u8,
+
+    /// Value of Sec-WebSocket-Key header
+    pub sec_websocket_key: Option<&'a str>,
+
+    /// Value of Sec-WebSocket-Version header
+    pub sec_websocket_version: Option<&'a str>,
+
+    headers: [Header<'a>; crate::consts::HTTP_MAX_HEADERS],
+    headers_len: usize,
+}
+
+#[cfg(feature = "http")]
+impl<'a> HttpRequest<'a> {
+    /// All request headers
+    pub fn headers(&self) -> &[Header<'a>] {
+        &self.headers[..self.headers_len]
+    }
+
+    /// Find header value by name (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&'a [u8]> {
+        self.headers()
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .map(|header| header.value)
+    }
+
+    /// Subprotocols requested by client (Sec-WebSocket-Protocol)
+    pub fn protocols(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.header_tokens("Sec-WebSocket-Protocol")
+    }
+
+    /// Extensions requested by client with their parameters (Sec-WebSocket-Extensions)
+    pub fn extensions(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.header_tokens("Sec-WebSocket-Extensions")
+    }
+
+    /// Comma separated values of all headers with given name
+    pub fn header_tokens<'b>(&'b self, name: &'b str) -> impl Iterator<Item = &'a str> + 'b {
+        crate::handshake::header_tokens(self.headers(), name)
+    }
 }
 
 impl<'a> WsRxFramer<'a> {
+    /// Create framer that doesn't enforce masking of received frames
+    ///
+    /// `new_server` or `new_client` should be preferred
     pub fn new(buf: &'a mut [u8]) -> Self {
+        let max_message_len = buf.len();
         Self {
             buf,
 
             current_header: None,
             write_offset: 0,
+            current_packet_start: 0,
             current_packet_end: 0,
             shift: false,
+            reassemble: false,
+            message_opcode: None,
+            message_len: 0,
+            utf8: Utf8Validator::new(),
+            chunk_offset: 0,
+            message_compressed: false,
+            #[cfg(feature = "deflate")]
+            inflater: None,
+            #[cfg(feature = "deflate")]
+            deflate_offer: None,
+            extensions: &mut [],
+            negotiated_extensions: 0,
+            max_message_len,
+            rules: RxRules::default(),
         }
     }
 
-    #[cfg(feature = "http")]
-    pub fn process_http_response<'b>(&'b mut self, n: usize) -> Option<HttpResponse> {
-        self.write_offset += n;
-
-        let mut headers = [httparse::EMPTY_HEADER; 16];
-        let mut resp = httparse::Response::new(&mut headers);
-        let res = resp.parse(&self.buf[..self.write_offset]).ok()?;
-
-        if res.is_complete() {
-            let code = resp.code.clone();
-            let mut offset = res.unwrap();
+    /// Create framer used by server (received frames must be masked)
+    pub fn new_server(buf: &'a mut [u8]) -> Self {
+        Self {
+            rules: RxRules {
+                expect_mask: Some(true),
+                ..RxRules::default()
+            },
+            ..Self::new(buf)
+        }
+    }
 
-            #[cfg(feature = "alloc")]
-            let mut headers_alloc = alloc::vec::Vec::<HttpHeaderOwned>::new();
-            for header in resp.headers {
-                if header.name == "Content-Length" {
-                    let content_length =
-                        usize::from_str_radix(core::str::from_utf8(header.value).ok()?, 10).ok()?;
+    /// Create framer used by client (received frames must not be masked)
+    pub fn new_client(buf: &'a mut [u8]) -> Self {
+        Self {
+            rules: RxRules {
+                expect_mask: Some(false),
+                ..RxRules::default()
+            },
+            ..Self::new(buf)
+        }
+    }
 
-                    offset += content_length;
-                }
+    /// Set maximum payload length of single data frame (unlimited by default)
+    ///
+    /// Larger frames are rejected as soon as their header is parsed
+    /// (`WsError::FrameTooLarge`). Frames can't be larger than internal buffer
+    /// unless they are received using `process_chunk`.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.rules.max_frame_len = max_frame_len;
+        self
+    }
 
-                #[cfg(feature = "alloc")]
-                if let Ok(value) = core::str::from_utf8(header.value) {
-                    headers_alloc.push(HttpHeaderOwned {
-                        name: alloc::string::ToString::to_string(&header.name),
-                        value: alloc::string::ToString::to_string(&value),
-                    });
-                }
-            }
+    /// Set maximum payload length of reassembled message (internal buffer
+    /// length by default)
+    ///
+    /// Also limits length of decompressed permessage-deflate message, which
+    /// can be larger than internal buffer (`WsError::MessageTooLarge`).
+    pub fn with_max_message_len(mut self, max_message_len: usize) -> Self {
+        self.max_message_len = max_message_len;
+        self
+    }
 
-            unsafe {
-                core::ptr::copy(
-                    self.buf.as_ptr().offset(offset as isize),
-                    self.buf.as_mut_ptr(),
-                    self.write_offset - offset,
-                );
-            }
+    /// Set maximum payload length of control frame (capped at 125 bytes in
+    /// strict mode)
+    ///
+    /// Larger frames are rejected with `WsError::ControlFrameTooLarge`, also
+    /// if strict mode is disabled.
+    pub fn with_max_control_len(mut self, max_control_len: usize) -> Self {
+        self.rules.max_control_len = max_control_len;
+        self
+    }
 
-            self.write_offset -= offset;
+    /// Enable strict validation of received frame headers (enabled by default)
+    ///
+    /// Strict mode rejects fragmented (`WsError::FragmentedControlFrame`) or
+    /// too long (`WsError::ControlFrameTooLarge`) control frames, reserved
+    /// opcodes (`WsError::ReservedOpcode`), non-minimal payload length
+    /// encodings (`WsError::NonMinimalLength`) and RSV bits that aren't used
+    /// by negotiated extension (`WsError::UnexpectedRsv`). Lenient mode
+    /// returns frames with reserved opcodes as `WsFrame::Unknown`.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.rules.strict = strict;
+        self
+    }
 
-            #[cfg(not(feature = "alloc"))]
-            if let Some(code) = code {
-                return Some(HttpResponse { status_code: code });
-            }
+    /// Enable reassembly of fragmented messages
+    ///
+    /// Data frames of fragmented message are coalesced inside internal buffer
+    /// and returned as single `Text`/`Binary` frame once final fragment arrives.
+    /// Control frames interleaved between fragments are returned immediately.
+    /// Without reassembly, data frames of fragmented message are returned as
+    /// `WsFrame::Fragment`.
+    pub fn with_reassembly(mut self, reassemble: bool) -> Self {
+        self.reassemble = reassemble;
+        self
+    }
 
-            #[cfg(feature = "alloc")]
-            if let Some(code) = code {
-                return Some(HttpResponse {
-                    status_code: code,
-                    headers: headers_alloc,
-                });
-            }
-        }
+    /// Set chain of extensions that can be negotiated
+    ///
+    /// Extensions aren't used until `negotiate_extensions` is called
+    pub fn with_extensions(mut self, extensions: &'a mut [&'a mut dyn Extension]) -> Self {
+        self.extensions = extensions;
+        self
+    }
 
-        None
+    /// Configure extensions from chain using extensions accepted by server
+    /// (`HttpResponse::extensions` on client, `HandshakeResponse::extensions` on server)
+    ///
+    /// Fails if accepted extension isn't in chain
+    pub fn negotiate_extensions<'e>(
+        &mut self,
+        accepted: impl IntoIterator<Item = &'e str>,
+    ) -> Result<(), HandshakeError> {
+        self.negotiated_extensions = extension::configure(self.extensions, accepted.into_iter())?;
+        Ok(())
     }
 
-    pub fn process_data<'b>(&'b mut self) -> Option<WsFrame<'b>> {
-        if self.shift {
-            // shift all data left (dispose parsed frame data)
-            unsafe {
-                core::ptr::copy(
-                    self.buf.as_ptr().offset(self.current_packet_end as isize),
-                    self.buf.as_mut_ptr(),
-                    self.write_offset - self.current_packet_end,
-                );
-            }
+    /// Set permessage-deflate parameters offered by client (see `DeflateParams::offer`)
+    ///
+    /// `process_handshake_response` rejects permessage-deflate accepted by
+    /// server if it wasn't offered.
+    #[cfg(feature = "deflate")]
+    pub fn with_deflate_offer(mut self, offer: DeflateParams) -> Self {
+        self.deflate_offer = Some(offer);
+        self
+    }
 
-            self.shift = false;
-            self.write_offset -= self.current_packet_end;
-            self.current_packet_end = 0;
+    /// Decompress received messages using negotiated permessage-deflate parameters
+    ///
+    /// `server` indicates if framer receives data from client. Enables
+    /// reassembly of fragmented messages, decompressed message can't be larger
+    /// than internal buffer. Compressed frames aren't decompressed by
+    /// `process_chunk` (RSV1 bit is set in returned chunk header). Fails if
+    /// decompressor doesn't fit into `params.max_memory` (such parameters are
+    /// never negotiated).
+    #[cfg(feature = "deflate")]
+    pub fn enable_deflate(
+        &mut self,
+        params: &DeflateParams,
+        server: bool,
+    ) -> Result<(), HandshakeError> {
+        if !params.can_inflate() {
+            return Err(HandshakeError::InvalidExtension);
         }
 
-        if self.current_header.is_none() {
-            let tmp_buf = self.buf[0..self.write_offset].as_mut();
-            let fin = (tmp_buf.get(0)? & 0b10000000) >> 7;
-            let rsv1 = (tmp_buf.get(0)? & 0b01000000) >> 6;
-            let rsv2 = (tmp_buf.get(0)? & 0b00100000) >> 5;
-            let rsv3 = (tmp_buf.get(0)? & 0b00010000) >> 4;
-            let opcode = tmp_buf.get(0)? & 0b00001111;
-            let mask = (tmp_buf.get(1)? & 0b10000000) >> 7;
-            let mut payload_len = (tmp_buf.get(1)? & 0b01111111) as usize;
-
-            let mut offset = 2;
-            if payload_len == 126 {
-                payload_len = u16::from_be_bytes(tmp_buf.get(2..4)?.try_into().unwrap()) as usize;
-                offset += 2;
-            } else if payload_len == 127 {
-                payload_len = u64::from_be_bytes(tmp_buf.get(2..10)?.try_into().unwrap()) as usize;
-                offset += 8;
-            }
+        self.inflater = Some(Inflater::new(params, server));
+        self.reassemble = true;
+        Ok(())
+    }
 
-            let mut masking_key = [0; 4];
-            if mask == 1 {
-                masking_key.copy_from_slice(&tmp_buf.get(offset..offset + 4)?);
-                offset += 4;
-            }
+    /// Parse http upgrade request (received by server)
+    ///
+    /// Request can be received in multiple reads (`n` is number of bytes read
+    /// into `mut_buf`). Data after request stays in buffer as websocket data.
+    #[cfg(feature = "http")]
+    pub fn process_http_request(&mut self, n: usize) -> Result<Option<HttpRequest<'_>>, WsError> {
+        self.dispose_parsed();
+        self.write_offset += n;
 
-            self.current_header = Some(WsFrameHeader {
-                fin: fin == 1,
-                rsv1: rsv1 == 1,
-                rsv2: rsv2 == 1,
-                rsv3: rsv3 == 1,
-                opcode,
-                mask: mask == 1,
-                masking_key,
-                payload_len,
-                offset,
-            });
+        let mut headers = [httparse::EMPTY_HEADER; crate::consts::HTTP_MAX_HEADERS];
+        let mut req = httparse::Request::new(&mut headers);
+        let buf = &self.buf[..self.write_offset];
+        let res = req.parse(buf).map_err(|_| WsError::InvalidHttp)?;
 
-            self.current_packet_end = offset + payload_len;
-        }
+        let httparse::Status::Complete(len) = res else {
+            if self.write_offset == self.buf.len() {
+                return Err(WsError::HttpTooLarge);
+            }
 
-        // if frame fully received
-        if self.write_offset >= self.current_packet_end && self.current_header.is_some() {
-            let header = self.current_header.take().unwrap();
-            self.shift = true; // shift on next invocation of process_data
+            return Ok(None);
+        };
 
-            return Some(WsFrame::from_data(
-                &header,
-                &mut self.buf[header.offset..header.offset + header.payload_len],
-            ));
-        }
+        // request is disposed from buffer on next call
+        self.shift = true;
+        self.current_packet_start = 0;
+        self.current_packet_end = len;
+
+        let mut request = HttpRequest {
+            method: req.method.unwrap_or_default(),
+            path: req.path.unwrap_or_default(),
+            version: req.version.unwrap_or_default(),
+            sec_websocket_key: None,
+            sec_websocket_version: None,
+            headers: [httparse::EMPTY_HEADER; crate::consts::HTTP_MAX_HEADERS],
+            headers_len: req.headers.len(),
+        };
 
-        None
-    }
+        request.headers[..req.headers.len()].copy_from_slice(req.headers);
+        request.sec_websocket_key = request
+            .header("Sec-WebSocket-Key")
+            .and_then(|value| core::str::from_utf8(value).ok());
+        request.sec_websocket_version = request
+            .header("Sec-WebSocket-Version")
+            .and_then(|value| core::str::from_utf8(value).ok());
 
-    pub fn mut_buf<'b>(&'b mut self) -> &'b mut [u8] {
-        self.buf[self.write_offset..].as_mut()
+        Ok(Some(request))
     }
 
-    pub fn revolve_write_offset(&mut self, n: usize) {
-        self.write_offset += n;
+    /// Parse http response without verifying it as handshake response
+    ///
+    /// Fails if response is malformed or doesn't fit into internal buffer.
+    /// `process_handshake_response` should be used for upgrade responses.
+    #[cfg(feature = "http")]
+    pub fn process_http_response(&mut self, n: usize) -> Result<Option<HttpResponse<'_>>, WsError> {
+        self.parse_http_response(n, None)
     }
-}
-
-/// Framer used to send data (websocket frames and http upgrade requests)
-pub struct WsTxFramer<'a> {
-    /// Internal buffer
-    buf: &'a mut [u8],
-
-    /// Boolean indicating if frames sent should be masked
-    mask: bool,
-}
 
-impl<'a> WsTxFramer<'a> {
-    pub fn new(mask: bool, buf: &'a mut [u8]) -> Self {
-        Self { buf, mask }
+    /// Parse server handshake response (received by client) and verify it
+    ///
+    /// `sec_websocket_key` is key sent in upgrade request
+    /// (see `WsTxFramer::sec_websocket_key`) and `protocols` are subprotocols
+    /// offered in that request. Checks status code, Upgrade and Connection
+    /// headers, Sec-WebSocket-Accept hash and subprotocol selected by server.
+    #[cfg(feature = "http")]
+    pub fn process_handshake_response<'b>(
+        &'b mut self,
+        n: usize,
+        sec_websocket_key: &str,
+        protocols: &[&'b str],
+    ) -> Result<Option<HttpResponse<'b>>, WsError> {
+        self.parse_http_response(n, Some((sec_websocket_key, protocols)))
     }
 
     #[cfg(feature = "http")]
-    pub fn generate_http_upgrade<'b>(
+    fn parse_http_response<'b>(
         &'b mut self,
-        host: &str,
-        path: &str,
-        additional_headers: Option<&[Header]>,
-    ) -> &'b [u8] {
-        let mut ws_key = [0u8; 16];
-        crate::rng_fill(&mut ws_key);
+        n: usize,
+        handshake: Option<(&str, &[&'b str])>,
+    ) -> Result<Option<HttpResponse<'b>>, WsError> {
+        self.dispose_parsed();
+        self.write_offset += n;
 
-        let mut ws_key_b64 = [0u8; crate::consts::WS_KEY_B64_LEN];
-        Base64Pad::encode_slice(&ws_key, &mut ws_key_b64);
+        let mut headers = [httparse::EMPTY_HEADER; crate::consts::HTTP_MAX_HEADERS];
+        let mut resp = httparse::Response::new(&mut headers);
+        let res = resp
+            .parse(&self.buf[..self.write_offset])
+            .map_err(|_| WsError::InvalidHttp)?;
 
-        self.buf[0..4].copy_from_slice(b"GET ");
-        let mut offset = 4 + path.as_bytes().len();
-        self.buf[4..offset].copy_from_slice(path.as_bytes());
-        self.buf[offset..offset + 11].copy_from_slice(b" HTTP/1.1\r\n");
-        offset += 11;
+        let httparse::Status::Complete(mut offset) = res else {
+            if self.write_offset == self.buf.len() {
+                return Err(WsError::HttpTooLarge);
+            }
 
-        let headers = [
-            Header {
-                name: "Host",
-                value: host.as_bytes(),
-            },
-            Header {
-                name: "Sec-WebSocket-Key",
-                value: &ws_key_b64,
-            },
-        ];
+            return Ok(None);
+        };
 
-        self.append_headers(&crate::consts::WS_DEFAULT_CONNECT_HEADERS, &mut offset);
-        self.append_headers(&headers, &mut offset);
-        if let Some(additional) = additional_headers {
-            self.append_headers(&additional, &mut offset);
+        let status_code = resp.code.ok_or(WsError::InvalidHttp)?;
+        let mut protocol = None;
+        #[cfg(feature = "deflate")]
+        let mut deflate = None;
+        if let Some((key, protocols)) = handshake {
+            protocol = crate::handshake::verify_response(status_code, resp.headers, key, protocols)
+                .map_err(WsError::Handshake)?;
+
+            #[cfg(feature = "deflate")]
+            {
+                let extensions =
+                    crate::handshake::header_tokens(resp.headers, "Sec-WebSocket-Extensions");
+                deflate = DeflateParams::from_response(extensions, self.deflate_offer.as_ref())
+                    .map_err(WsError::Handshake)?;
+            }
         }
 
-        self.buf[offset..offset + 2].copy_from_slice(b"\r\n");
-        &self.buf[0..offset + 2]
-    }
+        #[cfg(feature = "alloc")]
+        let mut headers_alloc = alloc::vec::Vec::<HttpHeaderOwned>::new();
+        for header in resp.headers.iter() {
+            if header.name.eq_ignore_ascii_case("Content-Length") {
+                let content_length: usize = core::str::from_utf8(header.value)
+                    .ok()
+                    .and_then(|value| value.trim().parse().ok())
+                    .ok_or(WsError::InvalidHttp)?;
 
-    #[cfg(feature = "http")]
-    pub fn generate_http_response<'b>(
-        &'b mut self,
-        status_code: u16,
-        status_text: &str,
-        headers: &[Header],
-    ) -> &'b [u8] {
-        self.buf[..9].copy_from_slice(b"HTTP/1.1 ");
-        let mut itoa = itoa::Buffer::new();
-        let status_code = itoa.format(status_code);
+                offset += content_length;
+            }
 
-        self.buf[9..9 + status_code.len()].copy_from_slice(status_code.as_bytes());
-        let mut offset = 9 + status_code.len();
-        self.buf[offset] = b' ';
-        self.buf[offset + 1..offset + 1 + status_text.len()]
-            .copy_from_slice(status_text.as_bytes());
-        offset += 1 + status_text.len();
-        self.buf[offset..offset + 2].copy_from_slice(b"\r\n");
-        offset += 2;
+            #[cfg(feature = "alloc")]
+            if let Ok(value) = core::str::from_utf8(header.value) {
+                headers_alloc.push(HttpHeaderOwned {
+                    name: alloc::string::ToString::to_string(&header.name),
+                    value: alloc::string::ToString::to_string(&value),
+                });
+            }
+        }
 
-        self.append_headers(&headers, &mut offset);
-        self.buf[offset..offset + 2].copy_from_slice(b"\r\n");
-        &self.buf[0..offset + 2]
-    }
+        // response body not fully received yet
+        if offset > self.write_offset {
+            return Ok(None);
+        }
 
-    fn append_headers<'b>(&'b mut self, headers: &[Header], offset: &mut usize) {
-        for header in headers {
-            self.buf[*offset..*offset + header.name.len()].copy_from_slice(header.name.as_bytes());
-            *offset += header.name.len();
+        // response is disposed from buffer on next call
+        self.shift = true;
+        self.current_packet_start = 0;
+        self.current_packet_end = offset;
 
-            self.buf[*offset..*offset + 2].copy_from_slice(b": ");
-            *offset += 2;
+        let mut response = HttpResponse {
+            status_code,
+            protocol,
+            #[cfg(feature = "deflate")]
+            deflate,
+            #[cfg(feature = "alloc")]
+            headers: headers_alloc,
+            raw_headers: [httparse::EMPTY_HEADER; crate::consts::HTTP_MAX_HEADERS],
+            raw_headers_len: resp.headers.len(),
+        };
 
-            self.buf[*offset..*offset + header.value.len()].copy_from_slice(header.value);
-            *offset += header.value.len();
+        response.raw_headers[..resp.headers.len()].copy_from_slice(resp.headers);
+        Ok(Some(response))
+    }
 
-            self.buf[*offset..*offset + 2].copy_from_slice(b"\r\n");
-            *offset += 2;
+    /// Parse next websocket frame from internal buffer
+    ///
+    /// Returns `Ok(None)` if more data is needed to complete the frame
+    pub fn process_data(&mut self) -> Result<Option<WsFrame<'_>>, WsError> {
+        match self.next_frame()? {
+            Some(parsed) => self.parsed_frame(parsed).map(Some),
+            None => Ok(None),
         }
     }
 
-    pub fn generate_packet<'b>(&'b mut self, header: &WsFrameHeader, data: &[u8]) -> &'b [u8] {
-        let first_byte = (header.fin as u8) << 7
-            | (header.rsv1 as u8) << 6
-            | (header.rsv2 as u8) << 5
-            | (header.rsv3 as u8) << 4
-            | header.opcode & 0x0F;
-        self.buf[0] = first_byte;
+    /// Parse next frame (or reassembled message) without borrowing its payload
+    ///
+    /// Returned value should be passed to `parsed_frame` before next call
+    pub(crate) fn next_frame(&mut self) -> Result<Option<ParsedFrame>, WsError> {
+        self.dispose_parsed();
+        loop {
+            // frames are parsed after already reassembled message payload
+            let frame_start = self.message_len;
+            if self.current_header.is_none() {
+                let Some(header) = WsFrameHeader::parse(
+                    &self.buf[frame_start..self.write_offset],
+                    self.rules.strict,
+                )?
+                else {
+                    return Ok(None);
+                };
+
+                header.validate(&self.rules, self.claimed_rsv())?;
+                self.check_message_len(&header, self.reassemble)?;
+                if header.payload_len > self.buf.len() - frame_start - header.offset {
+                    return Err(match self.message_opcode {
+                        Some(_) => WsError::MessageTooLarge,
+                        None => WsError::FrameTooLarge,
+                    });
+                }
 
-        let mut offset = 1;
-        match header.payload_len {
-            0..=125 => {
-                self.buf[offset] = (header.mask as u8) << 7 | header.payload_len as u8;
-                offset += 1;
+                self.current_packet_end = frame_start + header.offset + header.payload_len;
+                self.current_header = Some(header);
             }
-            126..crate::consts::U16_MAX => {
-                self.buf[offset] = (header.mask as u8) << 7 | 126;
-                self.buf[offset + 1..offset + 1 + 2]
-                    .copy_from_slice(&(header.payload_len as u16).to_be_bytes());
 
-                offset += 3;
+            // if frame not fully received
+            if self.write_offset < self.current_packet_end {
+                return Ok(None);
             }
-            crate::consts::U16_MAX.. => {
-                self.buf[offset] = (header.mask as u8) << 7 | 127;
-                self.buf[offset + 1..offset + 1 + 8]
-                    .copy_from_slice(&(header.payload_len as u64).to_be_bytes());
 
-                offset += 9;
+            let mut header = self.current_header.take().unwrap();
+            let payload_start = frame_start + header.offset;
+            let is_data = header.opcode < 8;
+
+            if self.negotiated_extensions > 0 {
+                self.decode_payload(&mut header, payload_start)?;
             }
-        }
 
-        if header.mask {
-            self.buf[offset..offset + 4].copy_from_slice(&header.masking_key);
-            offset += 4;
+            #[cfg(feature = "deflate")]
+            let compressed = self.is_compressed(&header)?;
+            #[cfg(not(feature = "deflate"))]
+            let compressed = false;
 
-            for d in data
-                .iter()
-                .enumerate()
-                .map(|(i, &x)| x ^ header.masking_key[i % 4])
-            {
-                self.buf[offset] = d;
-                offset += 1;
+            if !self.reassemble || !is_data {
+                self.shift = true; // shift on next invocation of process_data
+                self.current_packet_start = frame_start;
+
+                return Ok(Some(ParsedFrame::Frame(header)));
             }
-        } else {
-            self.buf[offset..offset + data.len()].copy_from_slice(data);
-            offset += data.len();
-        }
 
-        &self.buf[..offset]
-    }
+            header.check_sequence(self.message_opcode)?;
+            match (header.opcode, self.message_opcode) {
+                (opcode, None) if !header.fin => {
+                    self.message_opcode = Some(opcode);
+                    self.message_compressed = compressed;
+                    self.utf8.reset();
+                }
+                (_, None) => {
+                    self.shift = true;
+                    self.current_packet_start = frame_start;
+
+                    #[cfg(feature = "deflate")]
+                    if compressed {
+                        let payload = &mut self.buf[payload_start..self.current_packet_end];
+                        if header.mask {
+                            crate::apply_mask(payload, header.masking_key, 0);
+                        }
+
+                        self.inflate_message(payload_start, self.current_packet_end)?;
+                        return Ok(Some(ParsedFrame::Inflated(header.opcode)));
+                    }
+
+                    return Ok(Some(ParsedFrame::Frame(header)));
+                }
+                _ => {}
+            }
 
-    fn append_packet_data<'b>(
-        &'b mut self,
-        header: &WsFrameHeader,
-        data: &[u8],
-        mut offset: usize,
-    ) -> &'b [u8] {
-        if header.mask {
-            for d in data
-                .iter()
-                .enumerate()
-                .map(|(i, &x)| x ^ header.masking_key[i % 4])
-            {
-                self.buf[offset] = d;
-                offset += 1;
+            // append fragment payload to message (remove header from buffer)
+            let payload = &mut self.buf[payload_start..self.current_packet_end];
+            if header.mask {
+                crate::apply_mask(payload, header.masking_key, 0);
             }
-        } else {
-            self.buf[offset..offset + data.len()].copy_from_slice(data);
-            offset += data.len();
-        }
 
-        &self.buf[..offset]
-    }
+            // validate text fragments as they arrive (fail fast)
+            if self.message_opcode == Some(1) && !self.message_compressed {
+                self.utf8.feed(payload)?;
+            }
 
-    pub fn frame<'b>(&'b mut self, frame: WsFrame<'_>) -> &'b [u8] {
-        let mut masking_key = [0; 4];
-        if self.mask {
-            crate::rng_fill(&mut masking_key);
-        }
+            self.buf
+                .copy_within(payload_start..self.write_offset, frame_start);
+            self.write_offset -= header.offset;
+            self.message_len += header.payload_len;
+
+            if header.fin {
+                let opcode = self.message_opcode.take().unwrap();
+                self.shift = true;
+                self.current_packet_start = 0;
+                self.current_packet_end = self.message_len;
+                self.message_len = 0;
+
+                #[cfg(feature = "deflate")]
+                if core::mem::take(&mut self.message_compressed) {
+                    self.inflate_message(0, self.current_packet_end)?;
+                    return Ok(Some(ParsedFrame::Inflated(opcode)));
+                }
 
-        let (payload, size) = match frame {
-            WsFrame::Text(data) => (data.as_bytes(), data.as_bytes().len()),
-            WsFrame::Binary(data) => (data, data.len()),
-            WsFrame::Close(code, reason) => (&code.to_be_bytes()[..], 2 + reason.as_bytes().len()),
-            WsFrame::Ping(data) => (data, data.len()),
-            WsFrame::Pong(data) => (data, data.len()),
-            WsFrame::Unknown => todo!(),
-        };
+                if opcode == 1 {
+                    self.utf8.finish()?;
+                }
 
-        let header = WsFrameHeader {
-            fin: true,
-            rsv1: false,
-            rsv2: false,
-            rsv3: false,
-            opcode: frame.opcode(),
-            mask: self.mask,
-            masking_key,
-            payload_len: size,
-            offset: 0,
+                return Ok(Some(ParsedFrame::Message(opcode)));
+            }
+        }
+    }
+
+    /// Call `f` for every complete frame stored in internal buffer
+    ///
+    /// Unlike calling `process_data` in a loop, frames are walked in place and
+    /// buffer is compacted once at the end. Fragments of reassembled messages
+    /// are coalesced in place (only their payload is moved). Frames processed
+    /// by extensions fall back to `process_data`. Returns number of frames
+    /// passed to `f`.
+    pub fn for_each_frame(&mut self, mut f: impl FnMut(WsFrame<'_>)) -> Result<usize, WsError> {
+        self.dispose_parsed();
+
+        #[cfg(feature = "deflate")]
+        let fast_path = self.inflater.is_none();
+        #[cfg(not(feature = "deflate"))]
+        let fast_path = true;
+
+        let fast_path =
+            fast_path && self.negotiated_extensions == 0 && self.current_header.is_none();
+
+        // frames are parsed after already reassembled message payload
+        let start = self.message_len;
+        let mut count = 0;
+        let mut offset = start;
+        let mut message_start = 0;
+        let res = match fast_path {
+            true => self.walk_frames(&mut offset, &mut message_start, &mut count, &mut f),
+            false => Ok(()),
         };
 
-        let data = if let WsFrame::Close(_, reason) = frame {
-            let data = self.generate_packet(&header, payload);
-            let data_len = data.len();
+        // frames passed to `f` are dropped even if walk failed, so that they
+        // aren't delivered again (unfinished message is moved to buffer start)
+        if offset > start {
+            let message = message_start..message_start + self.message_len;
+            self.buf.copy_within(message, 0);
+            self.buf
+                .copy_within(offset..self.write_offset, self.message_len);
+            self.write_offset -= offset - self.message_len;
+        }
 
-            self.append_packet_data(&header, reason.as_bytes(), data_len)
-        } else {
-            self.generate_packet(&header, payload)
-        };
+        res?;
+        while let Some(frame) = self.process_data()? {
+            f(frame);
+            count += 1;
+        }
 
-        data
+        Ok(count)
     }
 
-    pub fn partial_frame<'b>(
-        &'b mut self,
-        frame: &WsFrame<'_>,
+    /// Pass complete frames starting at `offset` to `f` without moving them,
+    /// `offset` is advanced past every parsed frame
+    ///
+    /// Payload of fragments is appended to message reassembled at
+    /// `message_start`, so it's moved only over already parsed frames.
+    fn walk_frames(
+        &mut self,
         offset: &mut usize,
-    ) -> (&'b [u8], bool) {
-        let mut masking_key = [0; 4];
-        if self.mask {
-            crate::rng_fill(&mut masking_key);
-        }
+        message_start: &mut usize,
+        count: &mut usize,
+        f: &mut impl FnMut(WsFrame<'_>),
+    ) -> Result<(), WsError> {
+        while *offset < self.write_offset {
+            let data = &self.buf[*offset..self.write_offset];
+            let Some(header) = WsFrameHeader::parse(data, self.rules.strict)? else {
+                break;
+            };
+
+            // incomplete frame (checked before computing end, which could
+            // overflow on 32-bit targets)
+            if header.payload_len > data.len() - header.offset {
+                break;
+            }
 
-        let (payload, size) = match frame {
-            WsFrame::Text(data) => (data.as_bytes(), data.as_bytes().len()),
-            WsFrame::Binary(data) => (*data, data.len()),
-            WsFrame::Close(code, reason) => (&code.to_be_bytes()[..], 2 + reason.as_bytes().len()),
-            WsFrame::Ping(data) => (*data, data.len()),
-            WsFrame::Pong(data) => (*data, data.len()),
-            WsFrame::Unknown => todo!(),
+            let payload_start = *offset + header.offset;
+            let end = payload_start + header.payload_len;
+            header.validate(&self.rules, self.claimed_rsv())?;
+            self.check_message_len(&header, self.reassemble)?;
+
+            if !self.reassemble || header.opcode >= 8 {
+                f(WsFrame::from_data_tracked(
+                    &header,
+                    &mut self.buf[payload_start..end],
+                    &mut self.message_opcode,
+                    &mut self.utf8,
+                )?);
+                *count += 1;
+                *offset = end;
+                continue;
+            }
+
+            header.check_sequence(self.message_opcode)?;
+            if self.message_opcode.is_none() {
+                if header.fin {
+                    f(WsFrame::from_data(
+                        &header,
+                        &mut self.buf[payload_start..end],
+                    )?);
+                    *count += 1;
+                    *offset = end;
+                    continue;
+                }
+
+                self.message_opcode = Some(header.opcode);
+                self.utf8.reset();
+                *message_start = payload_start;
+            }
+
+            // append fragment payload to message
+            let payload = &mut self.buf[payload_start..end];
+            if header.mask {
+                crate::apply_mask(payload, header.masking_key, 0);
+            }
+
+            if self.message_opcode == Some(1) {
+                self.utf8.feed(payload)?;
+            }
+
+            let message_end = *message_start + self.message_len;
+            self.buf.copy_within(payload_start..end, message_end);
+            self.message_len += header.payload_len;
+            *offset = end;
+
+            if header.fin {
+                let opcode = self.message_opcode.take().unwrap();
+                let len = core::mem::take(&mut self.message_len);
+                if opcode == 1 {
+                    self.utf8.finish()?;
+                }
+
+                let message = &self.buf[*message_start..*message_start + len];
+                // SAFETY: every fragment was checked by incremental validator
+                f(unsafe { message_frame(opcode, message) });
+                *count += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return frame parsed by `next_frame`
+    pub(crate) fn parsed_frame(&mut self, parsed: ParsedFrame) -> Result<WsFrame<'_>, WsError> {
+        match parsed {
+            ParsedFrame::Frame(header) => WsFrame::from_data_tracked(
+                &header,
+                &mut self.buf[self.current_packet_start + header.offset..self.current_packet_end],
+                &mut self.message_opcode,
+                &mut self.utf8,
+            ),
+            ParsedFrame::Message(opcode) => {
+                // SAFETY: every fragment was checked by incremental validator
+                Ok(unsafe { message_frame(opcode, &self.buf[..self.current_packet_end]) })
+            }
+            #[cfg(feature = "deflate")]
+            ParsedFrame::Inflated(opcode) => {
+                let payload = self.inflater.as_ref().unwrap().output();
+                Ok(match opcode {
+                    1 => WsFrame::Text(
+                        core::str::from_utf8(payload).map_err(|_| WsError::InvalidUtf8)?,
+                    ),
+                    _ => WsFrame::Binary(payload),
+                })
+            }
+        }
+    }
+
+    /// Parse next chunk of frame payload from internal buffer
+    ///
+    /// Payload is returned as soon as any part of it arrives, so frames larger
+    /// than internal buffer can be received. Control frames are always returned
+    /// in a single chunk. Chunks of text frames are validated as utf-8.
+    ///
+    /// Shouldn't be mixed with `process_data` (reassembly setting is ignored).
+    /// Fails with `WsError::ExtensionsNegotiated` if any extension is
+    /// negotiated, because chunks can't be decoded by extensions.
+    pub fn process_chunk(&mut self) -> Result<Option<PayloadChunk<'_>>, WsError> {
+        if self.negotiated_extensions > 0 {
+            return Err(WsError::ExtensionsNegotiated);
+        }
+
+        self.dispose_parsed();
+        if self.current_header.is_none() {
+            let Some(header) =
+                WsFrameHeader::parse(&self.buf[..self.write_offset], self.rules.strict)?
+            else {
+                return Ok(None);
+            };
+
+            header.validate(&self.rules, self.claimed_rsv())?;
+            if header.opcode >= 8 && header.payload_len > self.buf.len() - header.offset {
+                return Err(WsError::FrameTooLarge);
+            }
+
+            header.check_sequence(self.message_opcode)?;
+            if matches!(header.opcode, 1 | 2) {
+                self.message_opcode = Some(header.opcode);
+                self.utf8.reset();
+            }
+
+            self.chunk_offset = 0;
+            self.current_header = Some(header);
+        }
+
+        let header = self.current_header.as_ref().unwrap();
+        let available = self.write_offset - header.offset;
+        let remaining = header.payload_len - self.chunk_offset;
+        if (header.opcode >= 8 && available < remaining) || (available == 0 && remaining > 0) {
+            return Ok(None);
+        }
+
+        let n = available.min(remaining);
+        let offset = self.chunk_offset;
+        let is_last = n == remaining;
+
+        let data = &mut self.buf[header.offset..header.offset + n];
+        if header.mask {
+            crate::apply_mask(data, header.masking_key, offset);
+        }
+
+        if header.opcode < 8 {
+            if self.message_opcode == Some(1) {
+                self.utf8.feed(data)?;
+            }
+
+            if is_last && header.fin && self.message_opcode.take() == Some(1) {
+                self.utf8.finish()?;
+            }
+        }
+
+        // header bytes are disposed together with first chunk
+        let mut chunk_header = header.clone();
+        chunk_header.offset = 0;
+
+        self.shift = true;
+        self.current_packet_start = 0;
+        self.current_packet_end = header.offset + n;
+        self.chunk_offset += n;
+        if is_last {
+            self.current_header = None;
+        } else {
+            self.current_header = Some(chunk_header.clone());
+        }
+
+        Ok(Some(PayloadChunk {
+            header: chunk_header,
+            data: &self.buf[self.current_packet_end - n..self.current_packet_end],
+            offset,
+            is_last,
+        }))
+    }
+
+    /// Check if data frame fits into reassembled message (if `message` is set)
+    fn check_message_len(&self, header: &WsFrameHeader, message: bool) -> Result<(), WsError> {
+        // peer controls payload length, so sum could overflow
+        if message
+            && header.opcode < 8
+            && header.payload_len > self.max_message_len.saturating_sub(self.message_len)
+        {
+            return Err(WsError::MessageTooLarge);
+        }
+
+        Ok(())
+    }
+
+    /// RSV bits claimed by negotiated extensions (and permessage-deflate)
+    fn claimed_rsv(&self) -> u8 {
+        #[allow(unused_mut)]
+        let mut claimed = extension::rsv_bits(&self.extensions[..self.negotiated_extensions]);
+        #[cfg(feature = "deflate")]
+        if self.inflater.is_some() {
+            claimed |= 0b100;
+        }
+
+        claimed
+    }
+
+    /// Unmask and decode payload of fully received frame using negotiated
+    /// extensions (in reverse chain order)
+    ///
+    /// Bytes removed by extensions are disposed from internal buffer
+    fn decode_payload(
+        &mut self,
+        header: &mut WsFrameHeader,
+        payload_start: usize,
+    ) -> Result<(), WsError> {
+        let payload = &mut self.buf[payload_start..self.current_packet_end];
+        if header.mask {
+            crate::apply_mask(payload, header.masking_key, 0);
+            header.mask = false;
+        }
+
+        let mut len = header.payload_len;
+        for extension in self.extensions[..self.negotiated_extensions]
+            .iter_mut()
+            .rev()
+        {
+            len = extension
+                .decode(header, &mut self.buf[payload_start..payload_start + len])?
+                .min(len);
+        }
+
+        let removed = header.payload_len - len;
+        if removed > 0 {
+            self.buf.copy_within(
+                self.current_packet_end..self.write_offset,
+                payload_start + len,
+            );
+            self.write_offset -= removed;
+            self.current_packet_end -= removed;
+            header.payload_len = len;
+        }
+
+        Ok(())
+    }
+
+    /// Check if frame starts compressed message (RSV1 bit set)
+    ///
+    /// RSV1 bit is allowed only in first frame of data message
+    #[cfg(feature = "deflate")]
+    fn is_compressed(&self, header: &WsFrameHeader) -> Result<bool, WsError> {
+        if self.inflater.is_none() || !header.rsv1 {
+            return Ok(false);
+        }
+
+        match header.opcode {
+            1 | 2 => Ok(true),
+            _ => Err(WsError::UnexpectedRsv),
+        }
+    }
+
+    /// Decompress message payload stored in internal buffer
+    #[cfg(feature = "deflate")]
+    fn inflate_message(&mut self, start: usize, end: usize) -> Result<(), WsError> {
+        let inflater = self.inflater.as_mut().unwrap();
+        inflater.inflate(&self.buf[start..end], self.max_message_len)?;
+        Ok(())
+    }
+
+    /// Dispose data of previously returned frame (or chunk)
+    fn dispose_parsed(&mut self) {
+        if self.shift {
+            // shift all data left (dispose parsed frame data)
+            self.buf.copy_within(
+                self.current_packet_end..self.write_offset,
+                self.current_packet_start,
+            );
+
+            self.shift = false;
+            self.write_offset -= self.current_packet_end - self.current_packet_start;
+            self.current_packet_end = 0;
+        }
+    }
+
+    pub fn mut_buf(&mut self) -> &mut [u8] {
+        self.buf[self.write_offset..].as_mut()
+    }
+
+    pub fn revolve_write_offset(&mut self, n: usize) {
+        self.write_offset += n;
+    }
+}
+
+/// Framer used to send data (websocket frames and http upgrade requests)
+pub struct WsTxFramer<'a> {
+    /// Internal buffer
+    buf: &'a mut [u8],
+
+    /// Boolean indicating if frames sent should be masked
+    mask: bool,
+
+    /// Sec-WebSocket-Key sent in last upgrade request
+    #[cfg(feature = "http")]
+    sec_websocket_key: Option<[u8; crate::consts::WS_KEY_B64_LEN]>,
+
+    /// Compressor of negotiated permessage-deflate extension
+    #[cfg(feature = "deflate")]
+    deflater: Option<Deflater>,
+
+    /// Extension chain (negotiated extensions are stored at the front)
+    extensions: &'a mut [&'a mut dyn Extension],
+
+    /// Number of negotiated extensions
+    negotiated_extensions: usize,
+
+    /// Frame started by `begin_frame` whose payload isn't fully generated yet
+    streamed_frame: Option<StreamedFrame>,
+}
+
+/// State of frame generated by `WsTxFramer::begin_frame`
+struct StreamedFrame {
+    masking_key: [u8; 4],
+
+    /// Masking key offset of next payload byte
+    offset: usize,
+
+    /// Number of payload bytes that still need to be generated
+    remaining: u64,
+}
+
+impl<'a> WsTxFramer<'a> {
+    pub fn new(mask: bool, buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            mask,
+            #[cfg(feature = "http")]
+            sec_websocket_key: None,
+            #[cfg(feature = "deflate")]
+            deflater: None,
+            extensions: &mut [],
+            negotiated_extensions: 0,
+            streamed_frame: None,
+        }
+    }
+
+    /// Set chain of extensions that can be negotiated
+    ///
+    /// All extensions are offered in `generate_http_upgrade`, but aren't used
+    /// until they are negotiated (see `negotiate_extensions`)
+    pub fn with_extensions(mut self, extensions: &'a mut [&'a mut dyn Extension]) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Configure extensions from chain using extensions accepted by server
+    /// (not needed after `generate_handshake_accept`)
+    ///
+    /// Fails if accepted extension isn't in chain
+    pub fn negotiate_extensions<'e>(
+        &mut self,
+        accepted: impl IntoIterator<Item = &'e str>,
+    ) -> Result<(), HandshakeError> {
+        self.negotiated_extensions = extension::configure(self.extensions, accepted.into_iter())?;
+        Ok(())
+    }
+
+    /// Compress sent messages using negotiated permessage-deflate parameters
+    ///
+    /// Only `Text` and `Binary` frames generated by `frame` (and its helpers)
+    /// are compressed. Framer that masks frames is treated as client. Messages
+    /// are sent uncompressed if compressor doesn't fit into `params.max_memory`
+    /// next to decompressor.
+    #[cfg(feature = "deflate")]
+    pub fn enable_deflate(&mut self, params: &DeflateParams) {
+        self.deflater = params
+            .can_deflate()
+            .then(|| Deflater::new(params, !self.mask));
+    }
+
+    /// Sec-WebSocket-Key generated by last `generate_http_upgrade` call
+    /// (used to verify server handshake response)
+    #[cfg(feature = "http")]
+    pub fn sec_websocket_key(&self) -> Option<&str> {
+        self.sec_websocket_key
+            .as_ref()
+            .and_then(|key| core::str::from_utf8(key).ok())
+    }
+
+    #[cfg(feature = "http")]
+    pub fn generate_http_upgrade<'b>(
+        &'b mut self,
+        host: &str,
+        path: &str,
+        protocols: &[&str],
+        additional_headers: Option<&[Header]>,
+    ) -> Result<&'b [u8], WsError> {
+        let mut ws_key = [0u8; 16];
+        crate::rng_fill(&mut ws_key);
+
+        let mut ws_key_b64 = [0u8; crate::consts::WS_KEY_B64_LEN];
+        Base64Pad::encode_slice(&ws_key, &mut ws_key_b64);
+        self.sec_websocket_key = Some(ws_key_b64);
+
+        let headers = [
+            Header {
+                name: "Host",
+                value: host.as_bytes(),
+            },
+            Header {
+                name: "Sec-WebSocket-Key",
+                value: &ws_key_b64,
+            },
+        ];
+
+        let additional_headers = additional_headers.unwrap_or(&[]);
+        let needed = 4
+            + path.len()
+            + 11
+            + headers_len(&crate::consts::WS_DEFAULT_CONNECT_HEADERS)
+            + headers_len(&headers)
+            + token_header_len("Sec-WebSocket-Protocol", protocols)
+            + extensions_header_len(self.extensions, true)
+            + headers_len(additional_headers)
+            + 2;
+        self.check_len(needed)?;
+
+        self.buf[0..4].copy_from_slice(b"GET ");
+        let mut offset = 4 + path.len();
+        self.buf[4..offset].copy_from_slice(path.as_bytes());
+        self.buf[offset..offset + 11].copy_from_slice(b" HTTP/1.1\r\n");
+        offset += 11;
+
+        self.append_headers(&crate::consts::WS_DEFAULT_CONNECT_HEADERS, &mut offset);
+        self.append_headers(&headers, &mut offset);
+        self.append_token_header("Sec-WebSocket-Protocol", protocols, &mut offset);
+        _ = append_extensions_header(self.buf, &mut offset, self.extensions, true);
+        self.append_headers(additional_headers, &mut offset);
+
+        self.buf[offset..offset + 2].copy_from_slice(b"\r\n");
+        Ok(&self.buf[0..offset + 2])
+    }
+
+    #[cfg(feature = "http")]
+    pub fn generate_http_response<'b>(
+        &'b mut self,
+        status_code: u16,
+        status_text: &str,
+        headers: &[Header],
+    ) -> Result<&'b [u8], WsError> {
+        self.generate_http_response_parts(status_code, status_text, &[headers], 0)
+            .map(|(data, _)| data)
+    }
+
+    /// Generate http response with headers concatenated from multiple lists
+    ///
+    /// First `extensions` extensions from chain are listed in
+    /// Sec-WebSocket-Extensions header (appended after other headers).
+    /// Returns response with range of that header value.
+    #[cfg(feature = "http")]
+    fn generate_http_response_parts<'b>(
+        &'b mut self,
+        status_code: u16,
+        status_text: &str,
+        headers: &[&[Header]],
+        extensions: usize,
+    ) -> Result<(&'b [u8], Range<usize>), WsError> {
+        let mut itoa = itoa::Buffer::new();
+        let status_code = itoa.format(status_code);
+
+        let headers_len: usize = headers
+            .iter()
+            .map(|headers| headers_len(headers))
+            .sum::<usize>()
+            + extensions_header_len(&self.extensions[..extensions], false);
+        let needed = 9 + status_code.len() + 1 + status_text.len() + 2 + headers_len + 2;
+        self.check_len(needed)?;
+
+        self.buf[..9].copy_from_slice(b"HTTP/1.1 ");
+        self.buf[9..9 + status_code.len()].copy_from_slice(status_code.as_bytes());
+        let mut offset = 9 + status_code.len();
+        self.buf[offset] = b' ';
+        self.buf[offset + 1..offset + 1 + status_text.len()]
+            .copy_from_slice(status_text.as_bytes());
+        offset += 1 + status_text.len();
+        self.buf[offset..offset + 2].copy_from_slice(b"\r\n");
+        offset += 2;
+
+        for headers in headers {
+            self.append_headers(headers, &mut offset);
+        }
+
+        let extensions =
+            append_extensions_header(self.buf, &mut offset, &self.extensions[..extensions], false);
+        self.buf[offset..offset + 2].copy_from_slice(b"\r\n");
+        Ok((&self.buf[0..offset + 2], extensions))
+    }
+
+    /// Validate websocket upgrade request and generate server response
+    ///
+    /// Generates 101 response if request is valid, 426 response if websocket
+    /// version isn't supported or 400 response otherwise.
+    #[cfg(feature = "http")]
+    pub fn generate_handshake_accept<'b>(
+        &'b mut self,
+        req: &HttpRequest<'_>,
+        options: &AcceptOptions<'b>,
+    ) -> Result<HandshakeResponse<'b>, WsError> {
+        let key = match crate::handshake::validate_request(req) {
+            Ok(key) => key,
+            Err(err) => {
+                let data = match err {
+                    HandshakeError::UnsupportedVersion => self.generate_http_response(
+                        426,
+                        "Upgrade Required",
+                        &[
+                            Header {
+                                name: "Sec-WebSocket-Version",
+                                value: b"13",
+                            },
+                            Header {
+                                name: "Content-Length",
+                                value: b"0",
+                            },
+                        ],
+                    )?,
+                    _ => self.generate_http_response(
+                        400,
+                        "Bad Request",
+                        &[Header {
+                            name: "Content-Length",
+                            value: b"0",
+                        }],
+                    )?,
+                };
+
+                return Ok(HandshakeResponse {
+                    data,
+                    protocol: None,
+                    extensions: "",
+                    #[cfg(feature = "deflate")]
+                    deflate: None,
+                    error: Some(err),
+                });
+            }
+        };
+
+        // first protocol from server preference list that was requested by client
+        let protocol = options
+            .protocols
+            .iter()
+            .find(|protocol| req.protocols().any(|p| p == **protocol))
+            .copied();
+
+        #[cfg(feature = "deflate")]
+        let deflate = options
+            .deflate
+            .as_ref()
+            .and_then(|config| DeflateParams::negotiate(req.extensions(), config));
+        #[cfg(feature = "deflate")]
+        let extensions = deflate.as_ref().map(DeflateParams::response);
+        #[cfg(feature = "deflate")]
+        let extensions_header = extensions.as_ref().map(|value| Header {
+            name: "Sec-WebSocket-Extensions",
+            value: value.as_bytes(),
+        });
+        #[cfg(not(feature = "deflate"))]
+        let extensions_header = None;
+
+        self.negotiated_extensions = extension::accept(self.extensions, req.extensions());
+        let accept = crate::process_sec_websocket_key(key);
+        let mut headers = [
+            Header {
+                name: "Upgrade",
+                value: b"websocket",
+            },
+            Header {
+                name: "Connection",
+                value: b"Upgrade",
+            },
+            Header {
+                name: "Sec-WebSocket-Accept",
+                value: &accept,
+            },
+            Header {
+                name: "Sec-WebSocket-Protocol",
+                value: b"",
+            },
+        ];
+
+        let headers_len = match protocol {
+            Some(protocol) => {
+                headers[3].value = protocol.as_bytes();
+                4
+            }
+            None => 3,
+        };
+
+        let (data, extensions) = self.generate_http_response_parts(
+            101,
+            "Switching Protocols",
+            &[
+                &headers[..headers_len],
+                extensions_header.as_slice(),
+                options.headers,
+            ],
+            self.negotiated_extensions,
+        )?;
+
+        let extensions = core::str::from_utf8(&data[extensions]).unwrap_or_default();
+
+        Ok(HandshakeResponse {
+            data,
+            protocol,
+            extensions,
+            #[cfg(feature = "deflate")]
+            deflate,
+            error: None,
+        })
+    }
+
+    /// Append headers to internal buffer
+    ///
+    /// Caller must ensure that buffer is large enough (see `headers_len`)
+    #[cfg(feature = "http")]
+    fn append_headers(&mut self, headers: &[Header], offset: &mut usize) {
+        for header in headers {
+            self.buf[*offset..*offset + header.name.len()].copy_from_slice(header.name.as_bytes());
+            *offset += header.name.len();
+
+            self.buf[*offset..*offset + 2].copy_from_slice(b": ");
+            *offset += 2;
+
+            self.buf[*offset..*offset + header.value.len()].copy_from_slice(header.value);
+            *offset += header.value.len();
+
+            self.buf[*offset..*offset + 2].copy_from_slice(b"\r\n");
+            *offset += 2;
+        }
+    }
+
+    /// Append header with comma separated list of tokens
+    /// (nothing is appended if list is empty)
+    ///
+    /// Caller must ensure that buffer is large enough (see `token_header_len`)
+    #[cfg(feature = "http")]
+    fn append_token_header(&mut self, name: &str, tokens: &[&str], offset: &mut usize) {
+        if tokens.is_empty() {
+            return;
+        }
+
+        self.buf[*offset..*offset + name.len()].copy_from_slice(name.as_bytes());
+        *offset += name.len();
+
+        for (i, token) in tokens.iter().enumerate() {
+            let separator: &[u8] = if i == 0 { b": " } else { b", " };
+            self.buf[*offset..*offset + 2].copy_from_slice(separator);
+            *offset += 2;
+
+            self.buf[*offset..*offset + token.len()].copy_from_slice(token.as_bytes());
+            *offset += token.len();
+        }
+
+        self.buf[*offset..*offset + 2].copy_from_slice(b"\r\n");
+        *offset += 2;
+    }
+
+    pub fn generate_packet<'b>(
+        &'b mut self,
+        header: &WsFrameHeader,
+        data: &[u8],
+    ) -> Result<&'b [u8], WsError> {
+        self.generate_packet_parts(header, &[data])
+    }
+
+    /// Generate packet with payload concatenated from multiple parts
+    /// (masking key offset continues between parts)
+    fn generate_packet_parts<'b>(
+        &'b mut self,
+        header: &WsFrameHeader,
+        parts: &[&[u8]],
+    ) -> Result<&'b [u8], WsError> {
+        self.check_streamed_frame()?;
+
+        let extensions = &mut self.extensions[..self.negotiated_extensions];
+        let len = write_packet(self.buf, header, parts, extensions)?;
+        Ok(&self.buf[..len])
+    }
+
+    pub fn frame<'b>(&'b mut self, frame: WsFrame<'_>) -> Result<&'b [u8], WsError> {
+        self.check_streamed_frame()?;
+
+        let mut masking_key = [0; 4];
+        if self.mask {
+            crate::rng_fill(&mut masking_key);
+        }
+
+        let mut code = [0; 2];
+        let parts = frame_parts(&frame, &mut code)?;
+
+        let header = WsFrameHeader {
+            fin: true,
+            rsv1: false,
+            rsv2: false,
+            rsv3: false,
+            opcode: frame.opcode(),
+            mask: self.mask,
+            masking_key,
+            payload_len: parts[0].len() + parts[1].len(),
+            offset: 0,
+        };
+
+        #[cfg(feature = "deflate")]
+        if let (Some(deflater), 1 | 2) = (self.deflater.as_mut(), header.opcode) {
+            let payload = deflater.deflate(parts[0])?;
+            let header = WsFrameHeader {
+                rsv1: true,
+                payload_len: payload.len(),
+                ..header
+            };
+
+            let extensions = &mut self.extensions[..self.negotiated_extensions];
+            let len = write_packet(self.buf, &header, &[payload], extensions)?;
+            return Ok(&self.buf[..len]);
+        }
+
+        self.generate_packet_parts(&header, &parts)
+    }
+
+    /// Generate next fragment of frame payload starting at `offset`
+    ///
+    /// Returns generated packet and if frame was split (more fragments
+    /// follow). Control frames are never split. Fails with
+    /// `WsError::InvalidStreamedFrame` if `offset` is past end of payload.
+    #[deprecated(note = "use `MessageWriter` instead")]
+    pub fn partial_frame<'b>(
+        &'b mut self,
+        frame: &WsFrame<'_>,
+        offset: &mut usize,
+    ) -> Result<(&'b [u8], bool), WsError> {
+        if frame.opcode() >= 8 {
+            *offset = frame.data().len();
+            return Ok((self.frame(frame.clone())?, false));
+        }
+
+        let mut masking_key = [0; 4];
+        if self.mask {
+            crate::rng_fill(&mut masking_key);
+        }
+
+        let payload = match frame {
+            WsFrame::Text(data) => data.as_bytes(),
+            WsFrame::Binary(data) => data,
+            _ => return Err(WsError::UnsupportedFrame),
         };
 
-        let payload = &payload[*offset..];
-        let mut size = size.saturating_sub(*offset);
+        // at least one payload byte must fit after header
+        self.check_len(17)?;
+
+        let payload = payload
+            .get(*offset..)
+            .ok_or(WsError::InvalidStreamedFrame)?;
+        let mut size = payload.len();
         let splitted = size + 16 > self.buf.len();
 
         let mut header = WsFrameHeader {
@@ -439,41 +1523,1297 @@ impl<'a> WsTxFramer<'a> {
         }
 
         *offset += size;
-        let data = if let WsFrame::Close(_, reason) = frame {
-            // BUG: close frames shouldnt be spllited
-            let data = self.generate_packet(&header, &payload[..size]);
-            let data_len = data.len();
+        let data = self.generate_packet(&header, &payload[..size])?;
+        Ok((data, splitted))
+    }
 
-            self.append_packet_data(&header, reason.as_bytes(), data_len)
-        } else {
-            self.generate_packet(&header, &payload[..size])
+    /// Generate header of unfragmented frame without copying payload
+    ///
+    /// Header and payload should be written one after another (e.g. using
+    /// `write_vectored`). Only `Text`, `Binary` and control frames (with at
+    /// most 125 bytes of payload) can be generated, close payload must contain
+    /// valid status code and utf-8 reason. Fails with
+    /// `WsError::MaskingRequired` if framer masks frames (see
+    /// `header_in_place`) and with `WsError::ExtensionsNegotiated` if payload
+    /// should be processed by negotiated extensions.
+    pub fn header_vectored<'p>(
+        &self,
+        opcode: u8,
+        payload: &'p [u8],
+    ) -> Result<(WsHeaderBuf, &'p [u8]), WsError> {
+        if self.mask {
+            return Err(WsError::MaskingRequired);
+        }
+
+        self.check_raw_frame(opcode, Some(payload), payload.len() as u64)?;
+        Ok((self.encode_header(opcode, payload.len(), [0; 4]), payload))
+    }
+
+    /// Generate header of unfragmented frame and mask payload in place
+    /// (if framer masks frames)
+    ///
+    /// Same as `header_vectored`, but can be used by client.
+    pub fn header_in_place<'p>(
+        &self,
+        opcode: u8,
+        payload: &'p mut [u8],
+    ) -> Result<(WsHeaderBuf, &'p [u8]), WsError> {
+        self.check_raw_frame(opcode, Some(payload), payload.len() as u64)?;
+
+        let mut masking_key = [0; 4];
+        if self.mask {
+            crate::rng_fill(&mut masking_key);
+            crate::apply_mask(payload, masking_key, 0);
+        }
+
+        Ok((
+            self.encode_header(opcode, payload.len(), masking_key),
+            payload,
+        ))
+    }
+
+    /// Check if unfragmented frame with payload that isn't processed by
+    /// framer can be generated
+    ///
+    /// `payload` is `None` if it will be streamed after header is generated.
+    fn check_raw_frame(
+        &self,
+        opcode: u8,
+        payload: Option<&[u8]>,
+        payload_len: u64,
+    ) -> Result<(), WsError> {
+        self.check_streamed_frame()?;
+
+        #[cfg(feature = "deflate")]
+        if self.deflater.is_some() {
+            return Err(WsError::ExtensionsNegotiated);
+        }
+
+        if self.negotiated_extensions > 0 {
+            return Err(WsError::ExtensionsNegotiated);
+        }
+
+        match opcode {
+            1 | 2 => Ok(()),
+            8..=10 if payload_len > 125 => Err(WsError::ControlFrameTooLarge),
+            // close payload can't be validated before it's streamed
+            8 => match payload {
+                Some(payload) => crate::parse_close_payload(payload).map(|_| ()),
+                None => Err(WsError::UnsupportedFrame),
+            },
+            9 | 10 => Ok(()),
+            _ => Err(WsError::UnsupportedFrame),
+        }
+    }
+
+    /// Generate header of unfragmented frame with payload of `total_len` bytes
+    ///
+    /// Payload is generated in chunks by `continue_payload`, so it doesn't
+    /// have to fit into internal buffer. Opcodes are validated the same way as
+    /// in `header_vectored`, close frames can't be streamed (their payload
+    /// can't be validated). Fails if payload of previous frame isn't finished,
+    /// no other frame can be generated until then.
+    pub fn begin_frame(&mut self, opcode: u8, total_len: u64) -> Result<&[u8], WsError> {
+        // most significant bit of 64-bit length must be 0 (RFC 6455 section 5.2)
+        if total_len > i64::MAX as u64 {
+            return Err(WsError::InvalidLength);
+        }
+
+        self.check_raw_frame(opcode, None, total_len)?;
+
+        let mut masking_key = [0; 4];
+        if self.mask {
+            crate::rng_fill(&mut masking_key);
+        }
+
+        let header = WsFrameHeader {
+            fin: true,
+            rsv1: false,
+            rsv2: false,
+            rsv3: false,
+            opcode,
+            mask: self.mask,
+            masking_key,
+            payload_len: 0,
+            offset: 0,
+        };
+
+        // payload length can exceed usize on 32-bit targets
+        let payload_len = usize::try_from(total_len).unwrap_or(usize::MAX);
+        self.check_len(WsFrameHeader::header_len(payload_len, self.mask))?;
+        let len = write_header_with_len(self.buf, &header, total_len);
+        if total_len > 0 {
+            self.streamed_frame = Some(StreamedFrame {
+                masking_key,
+                offset: 0,
+                remaining: total_len,
+            });
+        }
+
+        Ok(&self.buf[..len])
+    }
+
+    /// Generate next chunk of payload of frame started by `begin_frame`
+    ///
+    /// Chunk is masked using masking key offset of its position in payload.
+    /// Fails if chunk is longer than remaining payload.
+    pub fn continue_payload<'b>(&'b mut self, data: &[u8]) -> Result<&'b [u8], WsError> {
+        let Some(frame) = self.streamed_frame.as_mut() else {
+            return Err(WsError::InvalidStreamedFrame);
+        };
+
+        if data.len() as u64 > frame.remaining {
+            return Err(WsError::InvalidStreamedFrame);
+        }
+
+        if data.len() > self.buf.len() {
+            return Err(WsError::BufferTooSmall { needed: data.len() });
+        }
+
+        let payload = &mut self.buf[..data.len()];
+        payload.copy_from_slice(data);
+        if self.mask {
+            crate::apply_mask(payload, frame.masking_key, frame.offset);
+        }
+
+        frame.offset = (frame.offset + data.len()) % 4;
+        frame.remaining -= data.len() as u64;
+        if frame.remaining == 0 {
+            self.streamed_frame = None;
+        }
+
+        Ok(&self.buf[..data.len()])
+    }
+
+    /// Encode header of unfragmented frame into stack buffer
+    fn encode_header(&self, opcode: u8, payload_len: usize, masking_key: [u8; 4]) -> WsHeaderBuf {
+        let header = WsFrameHeader {
+            fin: true,
+            rsv1: false,
+            rsv2: false,
+            rsv3: false,
+            opcode,
+            mask: self.mask,
+            masking_key,
+            payload_len,
+            offset: 0,
         };
 
-        (data, splitted)
+        let mut buf = WsHeaderBuf {
+            data: [0; 14],
+            len: 0,
+        };
+        buf.len = write_header(&mut buf.data, &header);
+        buf
     }
 
     #[cfg(feature = "alloc")]
-    pub fn frame_owned<'b>(&'b mut self, frame: crate::WsFrameOwned) -> &'b [u8] {
+    pub fn frame_owned(&mut self, frame: crate::WsFrameOwned) -> Result<&[u8], WsError> {
         self.frame(frame.into_ref())
     }
 
-    pub fn text<'b>(&'b mut self, data: &str) -> &'b [u8] {
+    pub fn text<'b>(&'b mut self, data: &str) -> Result<&'b [u8], WsError> {
         self.frame(WsFrame::Text(data))
     }
 
-    pub fn binary<'b>(&'b mut self, data: &[u8]) -> &'b [u8] {
+    pub fn binary<'b>(&'b mut self, data: &[u8]) -> Result<&'b [u8], WsError> {
         self.frame(WsFrame::Binary(data))
     }
 
-    pub fn close<'b>(&'b mut self, code: u16, reason: &str) -> &'b [u8] {
+    /// Generate close frame
+    ///
+    /// Fails if status code is reserved or not allowed on the wire
+    pub fn close<'b>(&'b mut self, code: CloseCode, reason: &str) -> Result<&'b [u8], WsError> {
+        // close frame without status code can be generated using `frame`
+        if code == CloseCode::NoStatus {
+            return Err(WsError::InvalidCloseCode(code.as_u16()));
+        }
+
         self.frame(WsFrame::Close(code, reason))
     }
 
-    pub fn ping<'b>(&'b mut self, data: &[u8]) -> &'b [u8] {
+    pub fn ping<'b>(&'b mut self, data: &[u8]) -> Result<&'b [u8], WsError> {
         self.frame(WsFrame::Ping(data))
     }
 
-    pub fn pong<'b>(&'b mut self, data: &[u8]) -> &'b [u8] {
+    pub fn pong<'b>(&'b mut self, data: &[u8]) -> Result<&'b [u8], WsError> {
         self.frame(WsFrame::Pong(data))
     }
+
+    /// Check if payload of frame started by `begin_frame` is finished
+    fn check_streamed_frame(&self) -> Result<(), WsError> {
+        match self.streamed_frame {
+            Some(_) => Err(WsError::InvalidStreamedFrame),
+            None => Ok(()),
+        }
+    }
+
+    /// Check if internal buffer can hold `needed` bytes
+    fn check_len(&self, needed: usize) -> Result<(), WsError> {
+        if needed > self.buf.len() {
+            return Err(WsError::BufferTooSmall { needed });
+        }
+
+        Ok(())
+    }
+}
+
+/// Writer of fragmented message (RFC 6455 section 5.4)
+///
+/// Message payload is written in chunks of arbitrary size and every chunk is
+/// split into frames that fit into tx framer buffer. First frame carries
+/// message opcode, next ones are continuation frames and only the last one
+/// has FIN bit set. Writer doesn't borrow framer, so control frames can be
+/// generated between fragments. Fragments aren't compressed by
+/// permessage-deflate.
+#[derive(Debug, Clone)]
+pub struct MessageWriter {
+    opcode: u8,
+    started: bool,
+}
+
+impl MessageWriter {
+    /// Start `Text` (1) or `Binary` (2) message
+    ///
+    /// Control frames cannot be fragmented (`WsError::FragmentedControlFrame`)
+    pub fn new(opcode: u8) -> Result<Self, WsError> {
+        match opcode {
+            1 | 2 => Ok(Self {
+                opcode,
+                started: false,
+            }),
+            8..=15 => Err(WsError::FragmentedControlFrame),
+            _ => Err(WsError::UnsupportedFrame),
+        }
+    }
+
+    /// Generate non-final fragment from start of `data`
+    ///
+    /// Returns packet and number of consumed bytes (as much as fits into
+    /// framer buffer), should be called until all data is consumed.
+    pub fn write<'b>(
+        &mut self,
+        tx: &'b mut WsTxFramer<'_>,
+        data: &[u8],
+    ) -> Result<(&'b [u8], usize), WsError> {
+        self.fragment(tx, data, false)
+    }
+
+    /// Generate final fragment from `data`
+    ///
+    /// If `data` doesn't fit into framer buffer, non-final fragment is
+    /// generated instead. Message is finished once all data is consumed, then
+    /// writer can be used to send next message.
+    pub fn finish<'b>(
+        &mut self,
+        tx: &'b mut WsTxFramer<'_>,
+        data: &[u8],
+    ) -> Result<(&'b [u8], usize), WsError> {
+        self.fragment(tx, data, true)
+    }
+
+    fn fragment<'b>(
+        &mut self,
+        tx: &'b mut WsTxFramer<'_>,
+        data: &[u8],
+        fin: bool,
+    ) -> Result<(&'b [u8], usize), WsError> {
+        // with extensions, space for longest header is reserved (see `write_packet`)
+        let header_len = match tx.negotiated_extensions {
+            0 => WsFrameHeader::header_len(data.len().min(tx.buf.len()), tx.mask),
+            _ => WsFrameHeader::header_len(usize::MAX, tx.mask),
+        };
+
+        let len = data.len().min(tx.buf.len().saturating_sub(header_len));
+        if len == 0 && !data.is_empty() {
+            return Err(WsError::BufferTooSmall {
+                needed: header_len + 1,
+            });
+        }
+
+        let mut masking_key = [0; 4];
+        if tx.mask {
+            crate::rng_fill(&mut masking_key);
+        }
+
+        let fin = fin && len == data.len();
+        let header = WsFrameHeader {
+            fin,
+            rsv1: false,
+            rsv2: false,
+            rsv3: false,
+            opcode: if self.started { 0 } else { self.opcode },
+            mask: tx.mask,
+            masking_key,
+            payload_len: len,
+            offset: 0,
+        };
+
+        let packet = tx.generate_packet(&header, &data[..len])?;
+        self.started = !fin;
+        Ok((packet, len))
+    }
+}
+
+/// Write packet with payload concatenated from multiple parts into buffer
+/// (masking key offset continues between parts)
+///
+/// Payload is encoded by extensions before header is written. Returns length
+/// of written packet.
+pub(crate) fn write_packet(
+    buf: &mut [u8],
+    header: &WsFrameHeader,
+    parts: &[&[u8]],
+    extensions: &mut [&mut dyn Extension],
+) -> Result<usize, WsError> {
+    // length of encoded payload isn't known yet, so space for longest header is reserved
+    let payload_start = match extensions.is_empty() {
+        true => WsFrameHeader::header_len(header.payload_len, header.mask),
+        false => WsFrameHeader::header_len(usize::MAX, header.mask),
+    };
+
+    let data_len: usize = parts.iter().map(|part| part.len()).sum();
+    let needed = payload_start + data_len;
+    if needed > buf.len() {
+        return Err(WsError::BufferTooSmall { needed });
+    }
+
+    let mut offset = payload_start;
+    for part in parts {
+        buf[offset..offset + part.len()].copy_from_slice(part);
+        offset += part.len();
+    }
+
+    let mut header = header.clone();
+    let mut len = data_len;
+    for extension in extensions.iter_mut() {
+        len = extension.encode(&mut header, &mut buf[payload_start..], len)?;
+    }
+
+    if header.opcode >= 8 && len > 125 {
+        return Err(WsError::ControlFrameTooLarge);
+    }
+
+    header.payload_len = len;
+    let header_len = write_header(buf, &header);
+    buf.copy_within(payload_start..payload_start + len, header_len);
+    if header.mask {
+        crate::apply_mask(
+            &mut buf[header_len..header_len + len],
+            header.masking_key,
+            0,
+        );
+    }
+
+    Ok(header_len + len)
+}
+
+/// Split frame payload into parts (close frame payload is its code and reason)
+pub(crate) fn frame_parts<'f>(
+    frame: &WsFrame<'f>,
+    code: &'f mut [u8; 2],
+) -> Result<[&'f [u8]; 2], WsError> {
+    Ok(match *frame {
+        WsFrame::Text(data) => [data.as_bytes(), &[]],
+        WsFrame::Binary(data) => [data, &[]],
+        // close frame without status code has empty payload
+        WsFrame::Close(CloseCode::NoStatus, _) => [&[], &[]],
+        WsFrame::Close(close_code, _) if !close_code.is_allowed_on_wire() => {
+            return Err(WsError::InvalidCloseCode(close_code.as_u16()));
+        }
+        WsFrame::Close(close_code, reason) => {
+            *code = close_code.as_u16().to_be_bytes();
+            [code, reason.as_bytes()]
+        }
+        WsFrame::Ping(data) => [data, &[]],
+        WsFrame::Pong(data) => [data, &[]],
+        WsFrame::Unknown | WsFrame::Fragment { .. } => return Err(WsError::UnsupportedFrame),
+    })
+}
+
+/// Write frame header into buffer, returns header length
+///
+/// Caller must ensure that buffer is large enough (see `WsFrameHeader::header_len`)
+fn write_header(buf: &mut [u8], header: &WsFrameHeader) -> usize {
+    write_header_with_len(buf, header, header.payload_len as u64)
+}
+
+/// Return reassembled message as `Text` (opcode 1) or `Binary` frame
+///
+/// # Safety
+///
+/// Payload of text message must be valid utf-8
+unsafe fn message_frame(opcode: u8, payload: &[u8]) -> WsFrame<'_> {
+    match opcode {
+        1 => WsFrame::Text(core::str::from_utf8_unchecked(payload)),
+        _ => WsFrame::Binary(payload),
+    }
+}
+
+/// Write frame header with payload length that may not fit into `usize`
+fn write_header_with_len(buf: &mut [u8], header: &WsFrameHeader, payload_len: u64) -> usize {
+    const U16_MAX: u64 = crate::consts::U16_MAX as u64;
+
+    let first_byte = (header.fin as u8) << 7
+        | (header.rsv1 as u8) << 6
+        | (header.rsv2 as u8) << 5
+        | (header.rsv3 as u8) << 4
+        | header.opcode & 0x0F;
+    buf[0] = first_byte;
+
+    let mut offset = 1;
+    match payload_len {
+        0..=125 => {
+            buf[offset] = (header.mask as u8) << 7 | payload_len as u8;
+            offset += 1;
+        }
+        126..=U16_MAX => {
+            buf[offset] = (header.mask as u8) << 7 | 126;
+            buf[offset + 1..offset + 1 + 2].copy_from_slice(&(payload_len as u16).to_be_bytes());
+
+            offset += 3;
+        }
+        _ => {
+            buf[offset] = (header.mask as u8) << 7 | 127;
+            buf[offset + 1..offset + 1 + 8].copy_from_slice(&payload_len.to_be_bytes());
+
+            offset += 9;
+        }
+    }
+
+    if header.mask {
+        buf[offset..offset + 4].copy_from_slice(&header.masking_key);
+        offset += 4;
+    }
+
+    offset
+}
+
+/// Calculate length of serialized Sec-WebSocket-Extensions header listing
+/// extensions from chain (0 if chain is empty)
+#[cfg(feature = "http")]
+fn extensions_header_len(extensions: &[&mut dyn Extension], offer: bool) -> usize {
+    match extensions.is_empty() {
+        true => 0,
+        false => {
+            "Sec-WebSocket-Extensions: ".len() + extension::header_value_len(extensions, offer) + 2
+        }
+    }
+}
+
+/// Append Sec-WebSocket-Extensions header listing extensions from chain
+/// (nothing is appended if chain is empty)
+///
+/// Returns range of written header value. Caller must ensure that buffer is
+/// large enough (see `extensions_header_len`)
+#[cfg(feature = "http")]
+fn append_extensions_header(
+    buf: &mut [u8],
+    offset: &mut usize,
+    extensions: &[&mut dyn Extension],
+    offer: bool,
+) -> Range<usize> {
+    if extensions.is_empty() {
+        return *offset..*offset;
+    }
+
+    let name = b"Sec-WebSocket-Extensions: ";
+    buf[*offset..*offset + name.len()].copy_from_slice(name);
+    *offset += name.len();
+
+    let start = *offset;
+    extension::write_header_value(buf, offset, extensions, offer);
+    let value = start..*offset;
+    buf[*offset..*offset + 2].copy_from_slice(b"\r\n");
+    *offset += 2;
+    value
+}
+
+/// Calculate length of serialized header with comma separated list of tokens
+#[cfg(feature = "http")]
+fn token_header_len(name: &str, tokens: &[&str]) -> usize {
+    if tokens.is_empty() {
+        return 0;
+    }
+
+    let tokens_len: usize = tokens.iter().map(|token| token.len() + 2).sum();
+    name.len() + tokens_len + 2
+}
+
+/// Calculate length of serialized http headers
+#[cfg(feature = "http")]
+fn headers_len(headers: &[Header]) -> usize {
+    headers
+        .iter()
+        .map(|header| header.name.len() + 2 + header.value.len() + 2)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rx_frame<'a>(rx: &'a mut WsRxFramer, data: &[u8]) -> Result<Option<WsFrame<'a>>, WsError> {
+        rx.mut_buf()[..data.len()].copy_from_slice(data);
+        rx.revolve_write_offset(data.len());
+        rx.process_data()
+    }
+
+    #[test]
+    fn process_data_incomplete() {
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf);
+
+        assert_eq!(rx_frame(&mut rx, &[0x81]), Ok(None));
+        assert_eq!(rx_frame(&mut rx, &[0x03, b'a', b'b']), Ok(None));
+        assert!(matches!(
+            rx_frame(&mut rx, b"c"),
+            Ok(Some(WsFrame::Text("abc")))
+        ));
+    }
+
+    #[test]
+    fn process_data_errors() {
+        let cases: [(&[u8], WsError); 7] = [
+            (&[0x83, 0x00], WsError::ReservedOpcode(3)),
+            (&[0x82, 0x7E, 0x00, 0x05], WsError::NonMinimalLength),
+            (
+                &[0x82, 0x7F, 0x80, 0, 0, 0, 0, 0, 0, 0],
+                WsError::InvalidLength,
+            ),
+            (&[0x82, 0x7E, 0x01, 0x00], WsError::FrameTooLarge),
+            (&[0x88, 0x01, 0x03], WsError::InvalidClosePayload),
+            (&[0x88, 0x02, 0x03, 0xED], WsError::InvalidCloseCode(1005)),
+            (&[0x81, 0x02, 0xC3, 0x28], WsError::InvalidUtf8),
+        ];
+
+        for (data, err) in cases {
+            let mut buf = [0; 64];
+            let mut rx = WsRxFramer::new(&mut buf);
+            assert_eq!(rx_frame(&mut rx, data), Err(err));
+        }
+    }
+
+    #[test]
+    fn process_data_reassembly() {
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf).with_reassembly(true);
+
+        // "Hel" (masked), ping, "lo" (continuation), "!" (final continuation)
+        let data = [
+            0x01,
+            0x83,
+            1,
+            2,
+            3,
+            4,
+            b'H' ^ 1,
+            b'e' ^ 2,
+            b'l' ^ 3, //
+            0x89,
+            0x01,
+            b'p', //
+            0x00,
+            0x02,
+            b'l',
+            b'o', //
+            0x80,
+            0x01,
+            b'!', //
+            0x82,
+            0x01,
+            0xFF,
+        ];
+
+        assert_eq!(rx_frame(&mut rx, &data), Ok(Some(WsFrame::Ping(b"p"))));
+        assert_eq!(rx.process_data(), Ok(Some(WsFrame::Text("Hello!"))));
+        assert_eq!(rx.process_data(), Ok(Some(WsFrame::Binary(&[0xFF]))));
+        assert_eq!(rx.process_data(), Ok(None));
+    }
+
+    #[test]
+    fn process_data_reassembly_errors() {
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf).with_reassembly(true);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x80, 0x01, b'a']),
+            Err(WsError::UnexpectedContinuation)
+        );
+
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf).with_reassembly(true);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x01, 0x01, b'a', 0x81, 0x01, b'b']),
+            Err(WsError::ExpectedContinuation)
+        );
+
+        let mut buf = [0; 7];
+        let mut rx = WsRxFramer::new(&mut buf).with_reassembly(true);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x01, 0x03, b'a', b'b', b'c', 0x80, 0x03]),
+            Err(WsError::MessageTooLarge)
+        );
+    }
+
+    #[test]
+    fn for_each_frame() {
+        let mut buf = [0; 512];
+        let mut rx = WsRxFramer::new(&mut buf).with_reassembly(true);
+
+        // burst of small frames followed by fragmented message and partial frame
+        let mut data = [0x81, 0x01, b'a'].repeat(100);
+        data.extend_from_slice(&[0x02, 0x01, 1, 0x80, 0x01, 2, 0x89, 0x02, b'p']);
+        rx.mut_buf()[..data.len()].copy_from_slice(&data);
+        rx.revolve_write_offset(data.len());
+
+        let (mut texts, mut binary) = (0, false);
+        let count = rx
+            .for_each_frame(|frame| match frame {
+                WsFrame::Text("a") => texts += 1,
+                WsFrame::Binary(&[1, 2]) => binary = true,
+                frame => panic!("unexpected frame: {frame:?}"),
+            })
+            .unwrap();
+        assert_eq!((count, texts, binary), (101, 100, true));
+
+        assert_eq!(rx_frame(&mut rx, b"q"), Ok(Some(WsFrame::Ping(b"pq"))));
+
+        // fragmented message with interleaved ping, followed by burst of
+        // frames and unfinished message
+        let mut data = [0x01, 0x02, b'h', b'e', 0x89, 0x00, 0x80, 0x01, b'y'].to_vec();
+        data.extend_from_slice(&[0x81, 0x01, b'a'].repeat(50));
+        data.extend_from_slice(&[0x02, 0x01, 1, 0x00, 0x01, 2, 0x89, 0x00]);
+        rx.mut_buf()[..data.len()].copy_from_slice(&data);
+        rx.revolve_write_offset(data.len());
+
+        let (mut pings, mut texts, mut message) = (0, 0, false);
+        let count = rx
+            .for_each_frame(|frame| match frame {
+                WsFrame::Ping(&[]) => pings += 1,
+                WsFrame::Text("a") => texts += 1,
+                WsFrame::Text("hey") => message = pings == 1 && texts == 0,
+                frame => panic!("unexpected frame: {frame:?}"),
+            })
+            .unwrap();
+        assert_eq!((count, pings, texts, message), (53, 2, 50, true));
+
+        // unfinished message is kept at buffer start
+        let data = [0x80, 0x01, 3, 0x81, 0x01, b'b'];
+        rx.mut_buf()[..data.len()].copy_from_slice(&data);
+        rx.revolve_write_offset(data.len());
+
+        let mut frames = 0;
+        let count = rx
+            .for_each_frame(|frame| match (frames, frame) {
+                (0, WsFrame::Binary(&[1, 2, 3])) | (1, WsFrame::Text("b")) => frames += 1,
+                (_, frame) => panic!("unexpected frame: {frame:?}"),
+            })
+            .unwrap();
+        assert_eq!((count, frames), (2, 2));
+        assert_eq!(rx.mut_buf().len(), 512);
+
+        // frames passed before error aren't delivered again
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf);
+        let data = [0x81, 0x01, b'a', 0xC2, 0x00];
+        rx.mut_buf()[..data.len()].copy_from_slice(&data);
+        rx.revolve_write_offset(data.len());
+
+        let mut texts = 0;
+        for _ in 0..2 {
+            let res = rx.for_each_frame(|_| texts += 1);
+            assert_eq!(res, Err(WsError::UnexpectedRsv));
+        }
+        assert_eq!(texts, 1);
+
+        // announced length close to u64::MAX doesn't overflow
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf);
+        let data = [0x82, 0x7F, 0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        rx.mut_buf()[..data.len()].copy_from_slice(&data);
+        rx.revolve_write_offset(data.len());
+        assert_eq!(rx.for_each_frame(|_| {}), Err(WsError::FrameTooLarge));
+    }
+
+    #[test]
+    fn process_data_strict() {
+        let cases: [(&[u8], WsError); 5] = [
+            (&[0x09, 0x00], WsError::FragmentedControlFrame),
+            (&[0x8A, 0x7E, 0x00, 0x7E], WsError::ControlFrameTooLarge),
+            (&[0x8B, 0x00], WsError::ReservedOpcode(11)),
+            (&[0x82, 0x7E, 0x00, 0x05], WsError::NonMinimalLength),
+            (&[0xC2, 0x00], WsError::UnexpectedRsv),
+        ];
+
+        for (data, err) in cases {
+            let mut buf = [0; 256];
+            let mut rx = WsRxFramer::new(&mut buf);
+            assert_eq!(rx_frame(&mut rx, data), Err(err));
+
+            let mut buf = [0; 256];
+            let mut rx = WsRxFramer::new(&mut buf).with_strict(false);
+            assert_ne!(rx_frame(&mut rx, data), Err(err));
+        }
+
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf).with_strict(false);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x83, 0x01, 0x00]),
+            Ok(Some(WsFrame::Unknown))
+        );
+        assert_eq!(
+            rx_frame(&mut rx, &[0xE2, 0x7E, 0x00, 0x01, 0x07]),
+            Ok(Some(WsFrame::Binary(&[7])))
+        );
+    }
+
+    #[test]
+    fn process_data_masking() {
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new_server(&mut buf);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x81, 0x81, 1, 2, 3, 4, b'a' ^ 1]),
+            Ok(Some(WsFrame::Text("a")))
+        );
+        assert_eq!(
+            rx_frame(&mut rx, &[0x81, 0x01, b'a']),
+            Err(WsError::UnmaskedFrame)
+        );
+
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new_client(&mut buf);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x81, 0x01, b'a']),
+            Ok(Some(WsFrame::Text("a")))
+        );
+        assert_eq!(
+            rx_frame(&mut rx, &[0x89, 0x80, 1, 2, 3, 4]),
+            Err(WsError::MaskedFrame)
+        );
+    }
+
+    #[test]
+    fn process_data_limits() {
+        // rejected as soon as header arrives (payload not received yet)
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf).with_max_frame_len(8);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x82, 0x09]),
+            Err(WsError::FrameTooLarge)
+        );
+
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf)
+            .with_reassembly(true)
+            .with_max_message_len(4);
+        assert_eq!(rx_frame(&mut rx, &[0x02, 0x03, 1, 2, 3]), Ok(None));
+        assert_eq!(
+            rx_frame(&mut rx, &[0x80, 0x02]),
+            Err(WsError::MessageTooLarge)
+        );
+
+        // huge announced length doesn't overflow message length check
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf)
+            .with_reassembly(true)
+            .with_max_message_len(usize::MAX);
+        assert_eq!(rx_frame(&mut rx, &[0x02, 0x03, 1, 2, 3]), Ok(None));
+        assert_eq!(
+            rx_frame(
+                &mut rx,
+                &[0x80, 0x7F, 0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]
+            ),
+            Err(WsError::MessageTooLarge)
+        );
+
+        let mut buf = [0; 256];
+        let mut rx = WsRxFramer::new(&mut buf).with_max_control_len(200);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x89, 0x7E, 0x00, 0x7E]),
+            Err(WsError::ControlFrameTooLarge)
+        );
+
+        // configured limit is enforced in lenient mode too
+        let mut buf = [0; 256];
+        let mut rx = WsRxFramer::new(&mut buf)
+            .with_strict(false)
+            .with_max_control_len(1);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x89, 0x01, b'p']),
+            Ok(Some(WsFrame::Ping(b"p")))
+        );
+        assert_eq!(
+            rx_frame(&mut rx, &[0x89, 0x02, b'p', b'q']),
+            Err(WsError::ControlFrameTooLarge)
+        );
+        assert_eq!(
+            WsError::ControlFrameTooLarge.close_code(),
+            CloseCode::ProtocolError
+        );
+    }
+
+    #[test]
+    fn process_data_reassembly_utf8() {
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf).with_reassembly(true);
+
+        // "ł" (0xC5 0x82) split between fragments
+        let data = [0x01, 0x02, b'a', 0xC5, 0x80, 0x02, 0x82, b'b'];
+        assert_eq!(rx_frame(&mut rx, &data), Ok(Some(WsFrame::Text("ałb"))));
+
+        // invalid fragment rejected before message is finished
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf).with_reassembly(true);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x01, 0x02, 0xC5, 0x28]),
+            Err(WsError::InvalidUtf8)
+        );
+
+        // message ending inside code point
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf).with_reassembly(true);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x01, 0x01, b'a', 0x80, 0x01, 0xC5]),
+            Err(WsError::InvalidUtf8)
+        );
+
+        // without reassembly fragments are validated across message
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x01, 0x01, 0xC5, 0x80, 0x01, 0xBC]),
+            Ok(Some(WsFrame::Fragment {
+                opcode: 1,
+                fin: false,
+                data: &[0xC5]
+            }))
+        );
+        assert_eq!(
+            rx.process_data(),
+            Ok(Some(WsFrame::Fragment {
+                opcode: 0,
+                fin: true,
+                data: &[0xBC]
+            }))
+        );
+
+        // code point is invalid only together with previous fragment
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf);
+        let data = [0x01, 0x01, 0xC5, 0x89, 0x00, 0x80, 0x01, 0x28];
+        rx.mut_buf()[..data.len()].copy_from_slice(&data);
+        rx.revolve_write_offset(data.len());
+
+        let mut count = 0;
+        let res = rx.for_each_frame(|_| count += 1);
+        assert_eq!((res, count), (Err(WsError::InvalidUtf8), 2));
+    }
+
+    #[test]
+    fn close_frames() {
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x88, 0x00]),
+            Ok(Some(WsFrame::Close(CloseCode::NoStatus, "")))
+        );
+
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf);
+        assert_eq!(
+            rx_frame(&mut rx, &[0x88, 0x04, 0x0F, 0xA0, b'o', b'k']),
+            Ok(Some(WsFrame::Close(CloseCode::Private(4000), "ok")))
+        );
+
+        let mut buf = [0; 64];
+        let mut tx = WsTxFramer::new(false, &mut buf);
+        assert_eq!(
+            tx.close(CloseCode::Normal, "bye"),
+            Ok(&[0x88, 0x05, 0x03, 0xE8, b'b', b'y', b'e'][..])
+        );
+        assert_eq!(
+            tx.close(CloseCode::NoStatus, ""),
+            Err(WsError::InvalidCloseCode(1005))
+        );
+        assert_eq!(
+            tx.close(CloseCode::Library(1006), ""),
+            Err(WsError::InvalidCloseCode(1006))
+        );
+        assert_eq!(
+            tx.frame(WsFrame::Close(CloseCode::Library(1015), "")),
+            Err(WsError::InvalidCloseCode(1015))
+        );
+        assert_eq!(
+            tx.frame(WsFrame::Close(CloseCode::NoStatus, "")),
+            Ok(&[0x88, 0x00][..])
+        );
+    }
+
+    #[test]
+    fn process_chunk_large_frame() {
+        let key = [0x12, 0x34, 0x56, 0x78];
+        let payload: [u8; 300] = core::array::from_fn(|i| i as u8);
+
+        let mut data = [0; 4 + 4 + 300 + 3];
+        data[..4].copy_from_slice(&[0x82, 0xFE, 0x01, 0x2C]);
+        data[4..8].copy_from_slice(&key);
+        data[8..308].copy_from_slice(&payload);
+        crate::apply_mask(&mut data[8..308], key, 0);
+        data[308..].copy_from_slice(&[0x89, 0x01, b'p']);
+
+        let mut buf = [0; 16];
+        let mut rx = WsRxFramer::new(&mut buf);
+
+        let mut received = [0; 300];
+        let mut input = &data[..];
+        let mut ping_received = false;
+        while !input.is_empty() {
+            let n = rx.mut_buf().len().min(input.len());
+            rx.mut_buf()[..n].copy_from_slice(&input[..n]);
+            rx.revolve_write_offset(n);
+            input = &input[n..];
+
+            while let Some(chunk) = rx.process_chunk().unwrap() {
+                match chunk.header.opcode {
+                    2 => {
+                        received[chunk.offset..chunk.offset + chunk.data.len()]
+                            .copy_from_slice(chunk.data);
+                        assert_eq!(chunk.is_last, chunk.offset + chunk.data.len() == 300);
+                    }
+                    9 => {
+                        assert!(chunk.is_last);
+                        assert_eq!(chunk.data, b"p");
+                        ping_received = true;
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        assert_eq!(received, payload);
+        assert!(ping_received);
+    }
+
+    #[test]
+    fn tx_buffer_too_small() {
+        let mut buf = [0; 8];
+        let mut tx = WsTxFramer::new(true, &mut buf);
+        assert_eq!(tx.text("ab").map(<[u8]>::len), Ok(8));
+        assert_eq!(tx.text("abc"), Err(WsError::BufferTooSmall { needed: 9 }));
+
+        let mut buf = [0; 16];
+        let mut tx = WsTxFramer::new(false, &mut buf);
+        #[allow(deprecated)]
+        let res = tx.partial_frame(&WsFrame::Binary(&[0; 32]), &mut 0);
+        assert_eq!(res, Err(WsError::BufferTooSmall { needed: 17 }));
+
+        let mut buf = [0; 64];
+        let mut tx = WsTxFramer::new(false, &mut buf);
+        #[allow(deprecated)]
+        let res = tx.partial_frame(&WsFrame::Binary(&[0; 32]), &mut 33);
+        assert_eq!(res, Err(WsError::InvalidStreamedFrame));
+
+        let mut buf = [0; 2];
+        let mut tx = WsTxFramer::new(false, &mut buf);
+        let mut writer = MessageWriter::new(2).unwrap();
+        assert_eq!(
+            writer.write(&mut tx, &[0; 32]),
+            Err(WsError::BufferTooSmall { needed: 3 })
+        );
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn tx_http_buffer_too_small() {
+        let mut buf = [0; 32];
+        let mut tx = WsTxFramer::new(true, &mut buf);
+        assert_eq!(
+            tx.generate_http_response(101, "Switching Protocols", &[]),
+            Err(WsError::BufferTooSmall { needed: 36 })
+        );
+        assert_eq!(
+            tx.generate_http_upgrade("localhost", "/", &[], None),
+            Err(WsError::BufferTooSmall { needed: 148 })
+        );
+    }
+
+    #[test]
+    fn tx_message_writer() {
+        let mut tx_buf = [0; 8];
+        let mut tx = WsTxFramer::new(false, &mut tx_buf);
+        let mut rx_buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut rx_buf).with_reassembly(true);
+
+        let mut writer = MessageWriter::new(1).unwrap();
+        let (data, n) = writer.write(&mut tx, b"Hello, ").unwrap();
+        assert_eq!(
+            (data, n),
+            (&[0x01, 0x06, b'H', b'e', b'l', b'l', b'o', b','][..], 6)
+        );
+        assert_eq!(rx_frame(&mut rx, data), Ok(None));
+
+        // control frame between fragments
+        let data = tx.ping(b"p").unwrap();
+        assert_eq!(rx_frame(&mut rx, data), Ok(Some(WsFrame::Ping(b"p"))));
+
+        let mut payload = &b" world!"[..];
+        while !payload.is_empty() {
+            let (data, n) = writer.finish(&mut tx, payload).unwrap();
+            assert_eq!(data[0] & 0x0F, 0);
+            assert_eq!(data[0] & 0x80 != 0, n == payload.len());
+            payload = &payload[n..];
+
+            let res = rx_frame(&mut rx, data);
+            if payload.is_empty() {
+                assert_eq!(res, Ok(Some(WsFrame::Text("Hello, world!"))));
+            }
+        }
+
+        // next message starts with opcode again
+        let (data, _) = writer.finish(&mut tx, b"a").unwrap();
+        assert_eq!(data, &[0x81, 0x01, b'a']);
+
+        assert_eq!(
+            MessageWriter::new(8).map(|_| ()),
+            Err(WsError::FragmentedControlFrame)
+        );
+    }
+
+    #[test]
+    fn tx_streamed_frame() {
+        let payload: [u8; 300] = core::array::from_fn(|i| i as u8);
+        let mut tx_buf = [0; 16];
+        let mut tx = WsTxFramer::new(true, &mut tx_buf);
+        let mut rx_buf = [0; 512];
+        let mut rx = WsRxFramer::new_server(&mut rx_buf);
+
+        let header = tx.begin_frame(2, payload.len() as u64).unwrap();
+        assert_eq!(header.len(), 8);
+        assert_eq!(rx_frame(&mut rx, header), Ok(None));
+        assert_eq!(tx.begin_frame(2, 1), Err(WsError::InvalidStreamedFrame));
+
+        // no other frame can be interleaved with streamed payload
+        assert_eq!(tx.ping(b"p"), Err(WsError::InvalidStreamedFrame));
+        assert_eq!(
+            tx.frame(WsFrame::Binary(&[])).map(<[u8]>::len),
+            Err(WsError::InvalidStreamedFrame)
+        );
+        assert_eq!(
+            tx.header_in_place(2, &mut []).map(|_| ()),
+            Err(WsError::InvalidStreamedFrame)
+        );
+
+        // chunks of length not divisible by 4 (masking key offset rolls over)
+        for chunk in payload.chunks(7) {
+            let data = tx.continue_payload(chunk).unwrap();
+            let res = rx_frame(&mut rx, data);
+            if chunk.as_ptr_range().end == payload.as_ptr_range().end {
+                assert_eq!(res, Ok(Some(WsFrame::Binary(&payload))));
+            }
+        }
+
+        assert_eq!(
+            tx.continue_payload(&[0]),
+            Err(WsError::InvalidStreamedFrame)
+        );
+
+        // empty frame doesn't need any payload chunks
+        assert_eq!(tx.begin_frame(1, 0).map(<[u8]>::len), Ok(6));
+        assert_eq!(tx.continue_payload(&[]), Err(WsError::InvalidStreamedFrame));
+
+        // control frames must fit into single header, opcode must be known
+        assert_eq!(tx.begin_frame(9, 126), Err(WsError::ControlFrameTooLarge));
+        assert_eq!(tx.begin_frame(3, 1), Err(WsError::UnsupportedFrame));
+        assert_eq!(tx.begin_frame(8, 2), Err(WsError::UnsupportedFrame));
+        assert_eq!(tx.begin_frame(9, 1).map(<[u8]>::len), Ok(6));
+        assert!(tx.continue_payload(&[0]).is_ok());
+
+        // length is encoded as 64-bit even if it doesn't fit into usize
+        let mut tx = WsTxFramer::new(false, &mut tx_buf);
+        assert_eq!(
+            tx.begin_frame(2, 1 << 62),
+            Ok(&[0x82, 0x7F, 0x40, 0, 0, 0, 0, 0, 0, 0][..])
+        );
+
+        let mut tx = WsTxFramer::new(false, &mut tx_buf);
+        assert_eq!(tx.begin_frame(2, u64::MAX), Err(WsError::InvalidLength));
+        assert_eq!(tx.begin_frame(2, i64::MAX as u64).map(<[u8]>::len), Ok(10));
+    }
+
+    #[test]
+    fn tx_vectored() {
+        let payload = [0xAA; 300];
+        let tx = WsTxFramer::new(false, &mut []);
+        let (header, data) = tx.header_vectored(2, &payload).unwrap();
+        assert_eq!(&header[..], &[0x82, 0x7E, 0x01, 0x2C]);
+        assert_eq!(data.as_ptr(), payload.as_ptr());
+
+        let tx = WsTxFramer::new(true, &mut []);
+        assert_eq!(
+            tx.header_vectored(2, &payload).map(|_| ()),
+            Err(WsError::MaskingRequired)
+        );
+
+        let mut text = *b"hello";
+        let (header, data) = tx.header_in_place(1, &mut text).unwrap();
+        assert_eq!(header.len(), 6);
+
+        let mut rx_buf = [0; 64];
+        let mut rx = WsRxFramer::new_server(&mut rx_buf);
+        rx.mut_buf()[..6].copy_from_slice(&header);
+        rx.revolve_write_offset(6);
+        assert_eq!(rx_frame(&mut rx, data), Ok(Some(WsFrame::Text("hello"))));
+
+        let tx = WsTxFramer::new(false, &mut []);
+        for (opcode, len, err) in [
+            (9, 126, WsError::ControlFrameTooLarge),
+            (8, 1, WsError::InvalidClosePayload),
+            (0, 1, WsError::UnsupportedFrame),
+            (3, 1, WsError::UnsupportedFrame),
+            (0x19, 1, WsError::UnsupportedFrame),
+        ] {
+            assert_eq!(
+                tx.header_vectored(opcode, &payload[..len]).map(|_| ()),
+                Err(err)
+            );
+        }
+
+        // close payload is validated like in `frame`
+        for (payload, res) in [
+            (&[0x03, 0xE8, b'a'][..], Ok(())),
+            (&[0x03, 0xED], Err(WsError::InvalidCloseCode(1005))),
+            (&[0x03, 0xE8, 0xFF], Err(WsError::InvalidUtf8)),
+        ] {
+            assert_eq!(tx.header_vectored(8, payload).map(|_| ()), res);
+        }
+    }
+
+    #[test]
+    fn tx_rx_roundtrip() {
+        let payload = [0xAA; 0xFFFF];
+        let frames = [
+            WsFrame::Close(CloseCode::GoingAway, "restart"),
+            WsFrame::Binary(&payload),
+            WsFrame::Text("zażółć"),
+        ];
+
+        for frame in frames {
+            let mut tx_buf = [0; 0x10010];
+            let mut tx = WsTxFramer::new(true, &mut tx_buf);
+            let data = tx.frame(frame.clone()).unwrap();
+
+            let mut rx_buf = [0; 0x10010];
+            let mut rx = WsRxFramer::new(&mut rx_buf);
+            assert_eq!(rx_frame(&mut rx, data), Ok(Some(frame)));
+        }
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn deflate_messages() {
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf);
+        rx.enable_deflate(&DeflateParams::default(), false).unwrap();
+
+        // compressed "Hello" split into two fragments (RFC 7692 section 7.2.3.1)
+        let data = [
+            0x41, 0x03, 0xF2, 0x48, 0xCD, 0x80, 0x04, 0xC9, 0xC9, 0x07, 0x00,
+        ];
+        assert_eq!(rx_frame(&mut rx, &data), Ok(Some(WsFrame::Text("Hello"))));
+        assert_eq!(
+            rx_frame(&mut rx, &[0x89, 0x01, b'p', 0xC9, 0x00]),
+            Ok(Some(WsFrame::Ping(b"p")))
+        );
+        assert_eq!(rx.process_data(), Err(WsError::UnexpectedRsv));
+
+        let mut tx_buf = [0; 64];
+        let mut tx = WsTxFramer::new(true, &mut tx_buf);
+        tx.enable_deflate(&DeflateParams::default());
+        let data = tx.text("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+        assert_eq!(data[0], 0xC1);
+        assert!(data.len() < 40);
+
+        let mut buf = [0; 64];
+        let mut rx = WsRxFramer::new(&mut buf);
+        rx.enable_deflate(&DeflateParams::default(), true).unwrap();
+        assert_eq!(
+            rx_frame(&mut rx, data),
+            Ok(Some(WsFrame::Text(
+                "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+            )))
+        );
+
+        // compressor doesn't fit into memory limit, so messages are sent
+        // uncompressed (decompressor still fits)
+        let params = DeflateParams {
+            max_memory: DeflateParams::INFLATE_MEMORY,
+            ..Default::default()
+        };
+        let mut tx = WsTxFramer::new(true, &mut tx_buf);
+        tx.enable_deflate(&params);
+        assert_eq!(tx.text("a").map(|data| data[0]), Ok(0x81));
+        assert!(rx.enable_deflate(&params, true).is_ok());
+
+        let params = DeflateParams {
+            max_memory: DeflateParams::INFLATE_MEMORY - 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            rx.enable_deflate(&params, true),
+            Err(HandshakeError::InvalidExtension)
+        );
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn process_http_request() {
+        let request = b"GET /chat HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+            Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+            Sec-WebSocket-Protocol: chat, superchat\r\nSec-WebSocket-Protocol: mqtt\r\n\
+            Sec-WebSocket-Version: 13\r\n\r\n\x81\x02hi";
+
+        let mut buf = [0; 512];
+        let mut rx = WsRxFramer::new(&mut buf);
+
+        rx.mut_buf()[..request.len()].copy_from_slice(request);
+        assert!(rx.process_http_request(40).unwrap().is_none());
+
+        let req = rx
+            .process_http_request(request.len() - 40)
+            .unwrap()
+            .unwrap();
+        assert_eq!(req.method, "GET");
+        assert_eq!(req.path, "/chat");
+        assert_eq!(req.version, 1);
+        assert_eq!(req.sec_websocket_key, Some("dGhlIHNhbXBsZSBub25jZQ=="));
+        assert_eq!(req.sec_websocket_version, Some("13"));
+        assert_eq!(req.header("upgrade"), Some(&b"websocket"[..]));
+        assert!(req.protocols().eq(["chat", "superchat", "mqtt"]));
+        assert_eq!(req.extensions().count(), 0);
+
+        assert_eq!(rx.process_data(), Ok(Some(WsFrame::Text("hi"))));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn process_http_response_errors() {
+        let mut buf = [0; 32];
+        let mut rx = WsRxFramer::new(&mut buf);
+        let response = b"HTTP/1.1 200 OK\r\nX-Header: value\r\n";
+        rx.mut_buf().copy_from_slice(&response[..32]);
+        assert!(matches!(
+            rx.process_http_response(32),
+            Err(WsError::HttpTooLarge)
+        ));
+
+        let mut buf = [0; 32];
+        let mut rx = WsRxFramer::new(&mut buf);
+        rx.mut_buf()[..8].copy_from_slice(b"HTTP/1.1");
+        assert!(matches!(rx.process_http_response(4), Ok(None)));
+        rx.mut_buf()[..8].copy_from_slice(b"\0\0\0\0\r\n\r\n");
+        assert!(matches!(
+            rx.process_http_response(8),
+            Err(WsError::InvalidHttp)
+        ));
+    }
 }
@@ -0,0 +1,396 @@
+#[cfg(feature = "deflate")]
+use crate::DeflateParams;
+use crate::{framer::HttpRequest, HandshakeError};
+use httparse::Header;
+
+#[derive(Debug, Clone, Default)]
+/// Options used by server when accepting websocket upgrade request
+pub struct AcceptOptions<'a> {
+    /// Subprotocols supported by server (in order of preference)
+    pub protocols: &'a [&'a str],
+
+    /// Additional headers appended to 101 response
+    pub headers: &'a [Header<'a>],
+
+    /// Accept permessage-deflate offered by client (window bits are upper limits)
+    #[cfg(feature = "deflate")]
+    pub deflate: Option<DeflateParams>,
+}
+
+#[derive(Debug)]
+/// Result of `WsTxFramer::generate_handshake_accept`
+pub struct HandshakeResponse<'a> {
+    /// Http response that should be sent to the client
+    pub data: &'a [u8],
+
+    /// Selected subprotocol (from `AcceptOptions::protocols`)
+    pub protocol: Option<&'a str>,
+
+    /// Value of Sec-WebSocket-Extensions header listing extensions accepted
+    /// from tx framer chain
+    pub(crate) extensions: &'a str,
+
+    /// Negotiated permessage-deflate parameters
+    #[cfg(feature = "deflate")]
+    pub deflate: Option<DeflateParams>,
+
+    /// Reason why request was rejected (`None` if connection was upgraded)
+    pub error: Option<HandshakeError>,
+}
+
+impl<'a> HandshakeResponse<'a> {
+    pub fn is_accepted(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Extensions accepted from tx framer chain with their parameters
+    ///
+    /// Should be passed to `WsRxFramer::negotiate_extensions`
+    pub fn extensions(&self) -> impl Iterator<Item = &'a str> {
+        self.extensions
+            .split(',')
+            .map(str::trim)
+            .filter(|extension| !extension.is_empty())
+    }
+}
+
+/// Validate websocket upgrade request and return its Sec-WebSocket-Key
+pub(crate) fn validate_request<'a>(req: &HttpRequest<'a>) -> Result<&'a str, HandshakeError> {
+    if req.method != "GET" {
+        return Err(HandshakeError::InvalidMethod);
+    }
+
+    // RFC 6455 section 4.1 requires at least HTTP/1.1
+    if req.version != 1 {
+        return Err(HandshakeError::InvalidHttpVersion);
+    }
+
+    if !has_token(req.header_tokens("Upgrade"), "websocket") {
+        return Err(HandshakeError::InvalidUpgrade);
+    }
+
+    if !has_token(req.header_tokens("Connection"), "upgrade") {
+        return Err(HandshakeError::InvalidConnection);
+    }
+
+    let key = req
+        .sec_websocket_key
+        .map(str::trim)
+        .filter(|key| is_valid_key(key))
+        .ok_or(HandshakeError::InvalidKey)?;
+
+    if req.sec_websocket_version.map(str::trim) != Some("13") {
+        return Err(HandshakeError::UnsupportedVersion);
+    }
+
+    Ok(key)
+}
+
+/// Verify server handshake response (received by client)
+///
+/// Returns subprotocol selected by server (from offered protocols)
+pub(crate) fn verify_response<'p>(
+    status_code: u16,
+    headers: &[Header<'_>],
+    sec_websocket_key: &str,
+    protocols: &[&'p str],
+) -> Result<Option<&'p str>, HandshakeError> {
+    if status_code != 101 {
+        return Err(HandshakeError::InvalidStatus(status_code));
+    }
+
+    if !has_token(header_tokens(headers, "Upgrade"), "websocket") {
+        return Err(HandshakeError::InvalidUpgrade);
+    }
+
+    if !has_token(header_tokens(headers, "Connection"), "upgrade") {
+        return Err(HandshakeError::InvalidConnection);
+    }
+
+    if !is_valid_key(sec_websocket_key) {
+        return Err(HandshakeError::InvalidKey);
+    }
+
+    let expected = crate::process_sec_websocket_key(sec_websocket_key);
+    let accept = headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case("Sec-WebSocket-Accept"))
+        .map(|header| header.value.trim_ascii());
+
+    if accept != Some(&expected[..]) {
+        return Err(HandshakeError::InvalidAccept);
+    }
+
+    // server must select at most one of offered protocols
+    let mut selected = header_tokens(headers, "Sec-WebSocket-Protocol");
+    let protocol = match (selected.next(), selected.next()) {
+        (None, _) => None,
+        (Some(selected), None) => Some(
+            *protocols
+                .iter()
+                .find(|protocol| **protocol == selected)
+                .ok_or(HandshakeError::InvalidProtocol)?,
+        ),
+        (Some(_), Some(_)) => return Err(HandshakeError::InvalidProtocol),
+    };
+
+    Ok(protocol)
+}
+
+/// Comma separated values of all headers with given name (case-insensitive)
+pub(crate) fn header_tokens<'a, 'b>(
+    headers: &'b [Header<'a>],
+    name: &'b str,
+) -> impl Iterator<Item = &'a str> + 'b {
+    headers
+        .iter()
+        .filter(move |header| header.name.eq_ignore_ascii_case(name))
+        .filter_map(|header| core::str::from_utf8(header.value).ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// Check if token list contains token (case-insensitive)
+fn has_token<'a>(mut tokens: impl Iterator<Item = &'a str>, token: &str) -> bool {
+    tokens.any(|t| t.eq_ignore_ascii_case(token))
+}
+
+/// Check if key is base64 encoded 16-byte value
+fn is_valid_key(key: &str) -> bool {
+    let key = key.as_bytes();
+    key.len() == crate::consts::WS_KEY_B64_LEN
+        && key[..22]
+            .iter()
+            .all(|&c| c.is_ascii_alphanumeric() || c == b'+' || c == b'/')
+        && &key[22..] == b"=="
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{WsError, WsRxFramer, WsTxFramer};
+
+    fn accept(request: &[u8], check: impl FnOnce(&str, Option<HandshakeError>)) {
+        let mut rx_buf = [0; 512];
+        let mut rx = WsRxFramer::new(&mut rx_buf);
+        rx.mut_buf()[..request.len()].copy_from_slice(request);
+        let req = rx.process_http_request(request.len()).unwrap().unwrap();
+
+        let mut tx_buf = [0; 512];
+        let mut tx = WsTxFramer::new(false, &mut tx_buf);
+        let res = tx
+            .generate_handshake_accept(&req, &AcceptOptions::default())
+            .unwrap();
+
+        check(core::str::from_utf8(res.data).unwrap(), res.error);
+    }
+
+    #[test]
+    fn accept_valid_request() {
+        accept(
+            b"GET /chat HTTP/1.1\r\nHost: server.example.com\r\nUpgrade: WebSocket\r\n\
+            Connection: keep-alive, Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+            Sec-WebSocket-Version: 13\r\n\r\n",
+            |data, err| {
+                assert_eq!(err, None);
+                assert_eq!(
+                    data,
+                    "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                    Connection: Upgrade\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n"
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn reject_invalid_requests() {
+        accept(
+            b"GET / HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 8\r\n\r\n",
+            |data, err| {
+                assert_eq!(err, Some(HandshakeError::UnsupportedVersion));
+                assert!(data.starts_with("HTTP/1.1 426 Upgrade Required\r\n"));
+                assert!(data.contains("\r\nSec-WebSocket-Version: 13\r\n"));
+            },
+        );
+
+        accept(
+            b"GET / HTTP/1.1\r\nUpgrade: websocket\r\nConnection: keep-alive\r\n\
+            Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            |data, err| {
+                assert_eq!(err, Some(HandshakeError::InvalidConnection));
+                assert!(data.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+            },
+        );
+
+        accept(
+            b"GET / HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Key: c2hvcnQ=\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            |_, err| assert_eq!(err, Some(HandshakeError::InvalidKey)),
+        );
+
+        accept(b"POST / HTTP/1.1\r\n\r\n", |_, err| {
+            assert_eq!(err, Some(HandshakeError::InvalidMethod))
+        });
+
+        accept(
+            b"GET / HTTP/1.0\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            |data, err| {
+                assert_eq!(err, Some(HandshakeError::InvalidHttpVersion));
+                assert!(data.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+            },
+        );
+    }
+
+    #[test]
+    fn client_server_handshake() {
+        let mut client_tx_buf = [0; 512];
+        let mut client_tx = WsTxFramer::new(true, &mut client_tx_buf);
+        let request = client_tx
+            .generate_http_upgrade("localhost", "/", &["mqtt", "chat"], None)
+            .unwrap();
+
+        let mut server_rx_buf = [0; 512];
+        let mut server_rx = WsRxFramer::new(&mut server_rx_buf);
+        server_rx.mut_buf()[..request.len()].copy_from_slice(request);
+        let req = server_rx
+            .process_http_request(request.len())
+            .unwrap()
+            .unwrap();
+
+        let mut server_tx_buf = [0; 512];
+        let mut server_tx = WsTxFramer::new(false, &mut server_tx_buf);
+        let options = AcceptOptions {
+            protocols: &["chat", "graphql-transport-ws"],
+            ..Default::default()
+        };
+        let res = server_tx.generate_handshake_accept(&req, &options).unwrap();
+        assert!(res.is_accepted());
+        assert_eq!(res.protocol, Some("chat"));
+
+        let key = client_tx.sec_websocket_key().unwrap();
+        let mut client_rx_buf = [0; 512];
+        let mut client_rx = WsRxFramer::new(&mut client_rx_buf);
+        client_rx.mut_buf()[..res.data.len()].copy_from_slice(res.data);
+        let resp = client_rx
+            .process_handshake_response(res.data.len(), key, &["mqtt", "chat"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.status_code, 101);
+        assert_eq!(resp.protocol, Some("chat"));
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn deflate_handshake() {
+        let offer_params = DeflateParams {
+            server_no_context_takeover: true,
+            ..Default::default()
+        };
+        let offer = offer_params.offer().unwrap();
+        let headers = [Header {
+            name: "Sec-WebSocket-Extensions",
+            value: offer.as_bytes(),
+        }];
+
+        let mut client_tx_buf = [0; 512];
+        let mut client_tx = WsTxFramer::new(true, &mut client_tx_buf);
+        let request = client_tx
+            .generate_http_upgrade("localhost", "/", &[], Some(&headers))
+            .unwrap();
+
+        let mut server_rx_buf = [0; 512];
+        let mut server_rx = WsRxFramer::new(&mut server_rx_buf);
+        server_rx.mut_buf()[..request.len()].copy_from_slice(request);
+        let req = server_rx
+            .process_http_request(request.len())
+            .unwrap()
+            .unwrap();
+
+        let mut server_tx_buf = [0; 512];
+        let mut server_tx = WsTxFramer::new(false, &mut server_tx_buf);
+        let options = AcceptOptions {
+            deflate: Some(DeflateParams {
+                client_max_window_bits: 12,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let res = server_tx.generate_handshake_accept(&req, &options).unwrap();
+        let expected = DeflateParams {
+            server_no_context_takeover: true,
+            client_max_window_bits: 12,
+            ..Default::default()
+        };
+        assert_eq!(res.deflate, Some(expected));
+
+        let key = client_tx.sec_websocket_key().unwrap();
+        let mut client_rx_buf = [0; 512];
+        let mut client_rx = WsRxFramer::new(&mut client_rx_buf).with_deflate_offer(offer_params);
+        client_rx.mut_buf()[..res.data.len()].copy_from_slice(res.data);
+        let resp = client_rx
+            .process_handshake_response(res.data.len(), key, &[])
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.deflate, Some(expected));
+
+        // deflate accepted by server without client offer
+        let mut client_rx_buf = [0; 512];
+        let mut client_rx = WsRxFramer::new(&mut client_rx_buf);
+        client_rx.mut_buf()[..res.data.len()].copy_from_slice(res.data);
+        assert_eq!(
+            client_rx
+                .process_handshake_response(res.data.len(), key, &[])
+                .map(|res| res.is_some()),
+            Err(WsError::Handshake(HandshakeError::InvalidExtension))
+        );
+
+        // server without deflate config ignores offer
+        let res = server_tx
+            .generate_handshake_accept(&req, &AcceptOptions::default())
+            .unwrap();
+        assert_eq!(res.deflate, None);
+        assert!(!core::str::from_utf8(res.data)
+            .unwrap()
+            .contains("Sec-WebSocket-Extensions"));
+    }
+
+    #[test]
+    fn client_rejects_invalid_response() {
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        let cases: [(&[u8], HandshakeError); 4] = [
+            (
+                b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+                HandshakeError::InvalidStatus(200),
+            ),
+            (
+                b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n",
+                HandshakeError::InvalidConnection,
+            ),
+            (
+                b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                Connection: Upgrade\r\nSec-WebSocket-Accept: AAAALMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n",
+                HandshakeError::InvalidAccept,
+            ),
+            (
+                b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                Connection: Upgrade\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\
+                Sec-WebSocket-Protocol: chat\r\n\r\n",
+                HandshakeError::InvalidProtocol,
+            ),
+        ];
+
+        for (response, err) in cases {
+            let mut buf = [0; 512];
+            let mut rx = WsRxFramer::new(&mut buf);
+            rx.mut_buf()[..response.len()].copy_from_slice(response);
+            assert!(matches!(
+                rx.process_handshake_response(response.len(), key, &["mqtt"]),
+                Err(WsError::Handshake(e)) if e == err
+            ));
+        }
+    }
+}
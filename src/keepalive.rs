@@ -0,0 +1,151 @@
+use crate::{WsError, WsFrame};
+
+/// Monotonic clock used by `Keepalive` (milliseconds from arbitrary point)
+///
+/// Implemented for closures, so any time source can be used, e.g.
+/// `|| embassy_time::Instant::now().as_millis()`.
+pub trait Clock {
+    fn now_ms(&self) -> u64;
+}
+
+impl<F: Fn() -> u64> Clock for F {
+    fn now_ms(&self) -> u64 {
+        self()
+    }
+}
+
+#[cfg(feature = "std")]
+/// Clock based on `std::time::Instant` (counts from its creation)
+pub struct StdClock(std::time::Instant);
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self(std::time::Instant::now())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now_ms(&self) -> u64 {
+        self.0.elapsed().as_millis() as u64
+    }
+}
+
+/// Keepalive state (ping scheduler and pong replies)
+///
+/// Sans-IO component: received frames are passed to `on_frame` and `poll`
+/// should be called periodically (at least every `next_poll_ms`). Returned
+/// frames should be sent using tx framer (or `WsSession::send`).
+pub struct Keepalive<C> {
+    clock: C,
+    interval_ms: u64,
+    timeout_ms: u64,
+    last_rx: u64,
+    ping_sent: Option<u64>,
+}
+
+impl<C: Clock> Keepalive<C> {
+    /// Create keepalive that sends ping after `interval_ms` without received
+    /// frames and reports dead peer if nothing arrives `timeout_ms` after ping
+    pub fn new(clock: C, interval_ms: u64, timeout_ms: u64) -> Self {
+        let last_rx = clock.now_ms();
+        Self {
+            clock,
+            interval_ms,
+            timeout_ms,
+            last_rx,
+            ping_sent: None,
+        }
+    }
+
+    /// Handle received frame
+    ///
+    /// Any frame is treated as sign of life. Returns pong (with the same
+    /// payload) that should be sent if frame is ping.
+    pub fn on_frame<'f>(&mut self, frame: &WsFrame<'f>) -> Option<WsFrame<'f>> {
+        self.last_rx = self.clock.now_ms();
+        self.ping_sent = None;
+
+        match *frame {
+            WsFrame::Ping(data) => Some(WsFrame::Pong(data)),
+            _ => None,
+        }
+    }
+
+    /// Check timers, returns ping that should be sent
+    ///
+    /// Fails with `WsError::PeerTimeout` if peer didn't respond to ping
+    pub fn poll(&mut self) -> Result<Option<WsFrame<'static>>, WsError> {
+        let now = self.clock.now_ms();
+        if let Some(sent) = self.ping_sent {
+            if now.saturating_sub(sent) >= self.timeout_ms {
+                return Err(WsError::PeerTimeout);
+            }
+
+            return Ok(None);
+        }
+
+        if now.saturating_sub(self.last_rx) >= self.interval_ms {
+            self.ping_sent = Some(now);
+            return Ok(Some(WsFrame::Ping(&[])));
+        }
+
+        Ok(None)
+    }
+
+    /// Milliseconds until `poll` has something to do
+    pub fn next_poll_ms(&self) -> u64 {
+        let now = self.clock.now_ms();
+        let deadline = match self.ping_sent {
+            Some(sent) => sent.saturating_add(self.timeout_ms),
+            None => self.last_rx.saturating_add(self.interval_ms),
+        };
+
+        deadline.saturating_sub(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    #[test]
+    fn keepalive_ping_timeout() {
+        let now = Cell::new(0);
+        let mut keepalive = Keepalive::new(|| now.get(), 1000, 500);
+
+        assert_eq!(keepalive.poll(), Ok(None));
+        assert_eq!(keepalive.next_poll_ms(), 1000);
+
+        // received frames postpone ping
+        now.set(900);
+        assert_eq!(keepalive.on_frame(&WsFrame::Text("a")), None);
+        now.set(1500);
+        assert_eq!(keepalive.poll(), Ok(None));
+
+        now.set(1900);
+        assert_eq!(keepalive.poll(), Ok(Some(WsFrame::Ping(&[]))));
+        assert_eq!(keepalive.poll(), Ok(None));
+        assert_eq!(keepalive.next_poll_ms(), 500);
+
+        // pong arrived in time
+        now.set(2000);
+        assert_eq!(keepalive.on_frame(&WsFrame::Pong(&[])), None);
+        now.set(3000);
+        assert_eq!(keepalive.poll(), Ok(Some(WsFrame::Ping(&[]))));
+
+        now.set(3500);
+        assert_eq!(keepalive.poll(), Err(WsError::PeerTimeout));
+    }
+
+    #[test]
+    fn keepalive_pong_reply() {
+        let mut keepalive = Keepalive::new(|| 0, 1000, 500);
+        assert_eq!(
+            keepalive.on_frame(&WsFrame::Ping(b"abc")),
+            Some(WsFrame::Pong(b"abc"))
+        );
+    }
+}
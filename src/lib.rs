@@ -1,19 +1,79 @@
 #![no_std]
 
+pub use close::CloseCode;
 pub use crypto::process_sec_websocket_key;
-pub use framer::{WsRxFramer, WsTxFramer};
+pub use error::{ConnectionError, HandshakeError, WsError};
+pub use extension::Extension;
+pub use framer::{MessageWriter, PayloadChunk, WsHeaderBuf, WsRxFramer, WsTxFramer};
+pub use keepalive::{Clock, Keepalive};
+pub use ring::{WsRingFrame, WsRingRxFramer};
+pub use session::{CloseInitiator, SessionFrame, WsSession, WsState};
 pub use url::WsUrl;
+pub use utf8::Utf8Validator;
 
 #[cfg(feature = "alloc")]
 pub use url::WsUrlOwned;
 
+#[cfg(all(feature = "http", feature = "alloc"))]
+pub use framer::HttpHeaderOwned;
+
+#[cfg(feature = "http")]
+pub use framer::{HttpRequest, HttpResponse};
+
+#[cfg(feature = "http")]
+pub use handshake::{AcceptOptions, HandshakeResponse};
+
+#[cfg(feature = "deflate")]
+pub use deflate::DeflateParams;
+
+#[cfg(feature = "embedded-io-async")]
+pub use connection::WsConnection;
+
+#[cfg(feature = "std")]
+pub use keepalive::StdClock;
+
+#[cfg(feature = "std")]
+pub use stream::WsStream;
+
+#[cfg(feature = "tokio")]
+pub use codec::WsCodec;
+
+#[cfg(feature = "embedded-io")]
+pub use stream::WsEmbeddedStream;
+
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "std")]
+extern crate std;
+
+mod close;
+#[cfg(feature = "tokio")]
+mod codec;
+#[cfg(feature = "embedded-io-async")]
+mod connection;
 mod consts;
 mod crypto;
+#[cfg(feature = "deflate")]
+mod deflate;
+#[cfg(any(
+    feature = "std",
+    feature = "embedded-io",
+    feature = "embedded-io-async"
+))]
+mod driver;
+mod error;
+mod extension;
 mod framer;
+#[cfg(feature = "http")]
+mod handshake;
+mod keepalive;
+mod ring;
+mod session;
+#[cfg(any(feature = "std", feature = "embedded-io"))]
+mod stream;
 mod url;
+mod utf8;
 
 #[derive(Debug, Clone)]
 /// Websocket frame header
@@ -29,31 +89,208 @@ pub struct WsFrameHeader {
     offset: usize,
 }
 
+impl WsFrameHeader {
+    /// RSV bits of frame (`0b100` - RSV1, `0b010` - RSV2, `0b001` - RSV3)
+    pub fn rsv_bits(&self) -> u8 {
+        (self.rsv1 as u8) << 2 | (self.rsv2 as u8) << 1 | self.rsv3 as u8
+    }
+
+    /// Length of encoded header for given payload length
+    pub(crate) fn header_len(payload_len: usize, mask: bool) -> usize {
+        let len_bytes = match payload_len {
+            0..=125 => 0,
+            126..=0xFFFF => 2,
+            _ => 8,
+        };
+
+        2 + len_bytes + if mask { 4 } else { 0 }
+    }
+
+    /// Internal function to parse frame header from start of the buffer
+    ///
+    /// Reserved opcodes and non-minimal length encodings are rejected only in
+    /// `strict` mode. Returns `Ok(None)` if buffer doesn't contain whole header yet
+    pub(crate) fn parse(buf: &[u8], strict: bool) -> Result<Option<Self>, WsError> {
+        let (Some(&first), Some(&second)) = (buf.first(), buf.get(1)) else {
+            return Ok(None);
+        };
+
+        let opcode = first & 0b00001111;
+        if strict && matches!(opcode, 3..=7 | 11..=15) {
+            return Err(WsError::ReservedOpcode(opcode));
+        }
+
+        let mask = second & 0b10000000 != 0;
+        let mut payload_len = (second & 0b01111111) as u64;
+
+        let mut offset = 2;
+        if payload_len == 126 {
+            let Some(len) = buf.get(2..4) else {
+                return Ok(None);
+            };
+
+            payload_len = u16::from_be_bytes(len.try_into().unwrap()) as u64;
+            if strict && payload_len < 126 {
+                return Err(WsError::NonMinimalLength);
+            }
+
+            offset += 2;
+        } else if payload_len == 127 {
+            let Some(len) = buf.get(2..10) else {
+                return Ok(None);
+            };
+
+            payload_len = u64::from_be_bytes(len.try_into().unwrap());
+            if payload_len & (1 << 63) != 0 {
+                return Err(WsError::InvalidLength);
+            }
+
+            if strict && payload_len <= u16::MAX as u64 {
+                return Err(WsError::NonMinimalLength);
+            }
+
+            offset += 8;
+        }
+
+        let mut masking_key = [0; 4];
+        if mask {
+            let Some(key) = buf.get(offset..offset + 4) else {
+                return Ok(None);
+            };
+
+            masking_key.copy_from_slice(key);
+            offset += 4;
+        }
+
+        Ok(Some(WsFrameHeader {
+            fin: first & 0b10000000 != 0,
+            rsv1: first & 0b01000000 != 0,
+            rsv2: first & 0b00100000 != 0,
+            rsv3: first & 0b00010000 != 0,
+            opcode,
+            mask,
+            masking_key,
+            payload_len: usize::try_from(payload_len).map_err(|_| WsError::FrameTooLarge)?,
+            offset,
+        }))
+    }
+
+    /// Check if data frame continues (or starts) fragmented message correctly
+    ///
+    /// `message_opcode` is opcode of message that is being received (if any)
+    pub(crate) fn check_sequence(&self, message_opcode: Option<u8>) -> Result<(), WsError> {
+        match (self.opcode, message_opcode) {
+            (0, None) => Err(WsError::UnexpectedContinuation),
+            (1 | 2, Some(_)) => Err(WsError::ExpectedContinuation),
+            _ => Ok(()),
+        }
+    }
+
+    /// Validate header of received frame (before its payload arrives)
+    ///
+    /// Fragmentation and RFC length limit of control frames and RSV bits that
+    /// aren't in `claimed_rsv` are checked only in strict mode, configured
+    /// limits are checked in both modes.
+    pub(crate) fn validate(&self, rules: &RxRules, claimed_rsv: u8) -> Result<(), WsError> {
+        match rules.expect_mask {
+            Some(true) if !self.mask => return Err(WsError::UnmaskedFrame),
+            Some(false) if self.mask => return Err(WsError::MaskedFrame),
+            _ => {}
+        }
+
+        if self.opcode < 8 && self.payload_len > rules.max_frame_len {
+            return Err(WsError::FrameTooLarge);
+        }
+
+        if rules.strict && self.opcode >= 8 && !self.fin {
+            return Err(WsError::FragmentedControlFrame);
+        }
+
+        let max_control_len = match rules.strict {
+            true => rules.max_control_len.min(125),
+            false => rules.max_control_len,
+        };
+
+        if self.opcode >= 8 && self.payload_len > max_control_len {
+            return Err(WsError::ControlFrameTooLarge);
+        }
+
+        if rules.strict && self.rsv_bits() & !claimed_rsv != 0 {
+            return Err(WsError::UnexpectedRsv);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Validation rules of received frame headers (see `WsFrameHeader::validate`)
+pub(crate) struct RxRules {
+    /// If received headers are strictly validated (RFC 6455 section 5)
+    pub strict: bool,
+
+    /// If received frames must be masked (`None` if role isn't known)
+    pub expect_mask: Option<bool>,
+
+    /// Maximum payload length of single data frame
+    pub max_frame_len: usize,
+
+    /// Maximum payload length of control frame (capped at 125 bytes in
+    /// strict mode)
+    pub max_control_len: usize,
+}
+
+impl Default for RxRules {
+    fn default() -> Self {
+        Self {
+            strict: true,
+            expect_mask: None,
+            max_frame_len: usize::MAX,
+            max_control_len: usize::MAX,
+        }
+    }
+}
+
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// Websocket frame (packet)
 /// Stores reference to inner framer buffer
 pub enum WsFrame<'a> {
     Text(&'a str),
     Binary(&'a [u8]),
-    Close(u16, &'a str),
+    Close(CloseCode, &'a str),
     Ping(&'a [u8]),
     Pong(&'a [u8]),
     Unknown,
+
+    /// Fragment of message received without reassembly
+    ///
+    /// `opcode` is 0 for continuation frames. Text fragments are validated
+    /// across whole message, but single fragment can end inside code point.
+    Fragment {
+        opcode: u8,
+        fin: bool,
+        data: &'a [u8],
+    },
 }
 
 #[cfg(feature = "alloc")]
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 /// Websocket frame (packet)
 /// Owned version of WsFrame
 pub enum WsFrameOwned {
     Text(alloc::string::String),
     Binary(alloc::vec::Vec<u8>),
-    Close(u16, alloc::string::String),
+    Close(CloseCode, alloc::string::String),
     Ping(alloc::vec::Vec<u8>),
     Pong(alloc::vec::Vec<u8>),
     Unknown,
+    Fragment {
+        opcode: u8,
+        fin: bool,
+        data: alloc::vec::Vec<u8>,
+    },
 }
 
 impl<'a> WsFrame<'a> {
@@ -66,28 +303,71 @@ impl<'a> WsFrame<'a> {
             WsFrame::Ping(_) => 9,
             WsFrame::Pong(_) => 10,
             WsFrame::Unknown => 0,
+            WsFrame::Fragment { opcode, .. } => *opcode,
         }
     }
 
     /// Internal function to parse frame from header and buffer data
-    pub(crate) fn from_data(header: &WsFrameHeader, buf: &'a mut [u8]) -> Self {
+    pub(crate) fn from_data(header: &WsFrameHeader, buf: &'a mut [u8]) -> Result<Self, WsError> {
         if header.mask {
-            for (i, x) in buf.iter_mut().enumerate() {
-                let key = header.masking_key[i % 4];
-                *x ^= key;
-            }
+            apply_mask(buf, header.masking_key, 0);
         }
 
-        match header.opcode {
-            1 => Self::Text(unsafe { core::str::from_utf8_unchecked(buf) }),
+        if header.opcode < 3 && (!header.fin || header.opcode == 0) {
+            return Ok(Self::Fragment {
+                opcode: header.opcode,
+                fin: header.fin,
+                data: buf,
+            });
+        }
+
+        Ok(match header.opcode {
+            1 => Self::Text(core::str::from_utf8(buf).map_err(|_| WsError::InvalidUtf8)?),
             2 => Self::Binary(buf),
-            8 => Self::Close(u16::from_be_bytes([buf[0], buf[1]]), unsafe {
-                core::str::from_utf8_unchecked(&buf[2..])
-            }),
+            8 => {
+                let (code, reason) = parse_close_payload(buf)?;
+                Self::Close(code, reason)
+            }
             9 => Self::Ping(buf),
             10 => Self::Pong(buf),
             _ => Self::Unknown,
+        })
+    }
+
+    /// Parse frame received without reassembly and track fragmented message
+    ///
+    /// `message_opcode` is opcode of message whose fragments are received,
+    /// text fragments are validated incrementally across whole message.
+    pub(crate) fn from_data_tracked(
+        header: &WsFrameHeader,
+        buf: &'a mut [u8],
+        message_opcode: &mut Option<u8>,
+        utf8: &mut Utf8Validator,
+    ) -> Result<Self, WsError> {
+        if header.opcode < 8 {
+            header.check_sequence(*message_opcode)?;
         }
+
+        let frame = Self::from_data(header, buf)?;
+        if let Self::Fragment { opcode, fin, data } = frame {
+            if opcode != 0 {
+                *message_opcode = Some(opcode);
+                utf8.reset();
+            }
+
+            if *message_opcode == Some(1) {
+                utf8.feed(data)?;
+                if fin {
+                    utf8.finish()?;
+                }
+            }
+
+            if fin {
+                *message_opcode = None;
+            }
+        }
+
+        Ok(frame)
     }
 
     /// Helper to return data bytes from frame (if you dont care about frame type)
@@ -99,6 +379,25 @@ impl<'a> WsFrame<'a> {
             WsFrame::Ping(byt) => byt,
             WsFrame::Pong(byt) => byt,
             WsFrame::Unknown => &[],
+            WsFrame::Fragment { data, .. } => data,
+        }
+    }
+
+    /// Copy frame data into owned frame (releases framer buffer borrow)
+    #[cfg(feature = "alloc")]
+    pub fn into_owned(self) -> WsFrameOwned {
+        match self {
+            WsFrame::Text(str) => WsFrameOwned::Text(str.into()),
+            WsFrame::Binary(byt) => WsFrameOwned::Binary(byt.into()),
+            WsFrame::Close(code, reason) => WsFrameOwned::Close(code, reason.into()),
+            WsFrame::Ping(byt) => WsFrameOwned::Ping(byt.into()),
+            WsFrame::Pong(byt) => WsFrameOwned::Pong(byt.into()),
+            WsFrame::Unknown => WsFrameOwned::Unknown,
+            WsFrame::Fragment { opcode, fin, data } => WsFrameOwned::Fragment {
+                opcode,
+                fin,
+                data: data.into(),
+            },
         }
     }
 }
@@ -107,16 +406,48 @@ impl<'a> WsFrame<'a> {
 impl<'a> WsFrameOwned {
     pub fn into_ref(&'a self) -> WsFrame<'a> {
         match self {
-            WsFrameOwned::Text(string) => WsFrame::Text(&string),
-            WsFrameOwned::Binary(vec) => WsFrame::Binary(&vec),
-            WsFrameOwned::Close(code, reason) => WsFrame::Close(*code, &reason),
-            WsFrameOwned::Ping(vec) => WsFrame::Ping(&vec),
-            WsFrameOwned::Pong(vec) => WsFrame::Pong(&vec),
+            WsFrameOwned::Text(string) => WsFrame::Text(string),
+            WsFrameOwned::Binary(vec) => WsFrame::Binary(vec),
+            WsFrameOwned::Close(code, reason) => WsFrame::Close(*code, reason),
+            WsFrameOwned::Ping(vec) => WsFrame::Ping(vec),
+            WsFrameOwned::Pong(vec) => WsFrame::Pong(vec),
             WsFrameOwned::Unknown => WsFrame::Unknown,
+            WsFrameOwned::Fragment { opcode, fin, data } => WsFrame::Fragment {
+                opcode: *opcode,
+                fin: *fin,
+                data,
+            },
         }
     }
 }
 
+/// Parse status code and reason from close frame payload
+pub(crate) fn parse_close_payload(payload: &[u8]) -> Result<(CloseCode, &str), WsError> {
+    match payload {
+        [] => Ok((CloseCode::NoStatus, "")),
+        [_] => Err(WsError::InvalidClosePayload),
+        [hi, lo, reason @ ..] => {
+            let code = u16::from_be_bytes([*hi, *lo]);
+            let close_code = CloseCode::from_u16(code)
+                .filter(CloseCode::is_allowed_on_wire)
+                .ok_or(WsError::InvalidCloseCode(code))?;
+
+            let reason = core::str::from_utf8(reason).map_err(|_| WsError::InvalidUtf8)?;
+            Ok((close_code, reason))
+        }
+    }
+}
+
+/// Xor payload data with masking key
+///
+/// Offset is position of first byte of `buf` inside frame payload
+pub(crate) fn apply_mask(buf: &mut [u8], masking_key: [u8; 4], offset: usize) {
+    for (i, x) in buf.iter_mut().enumerate() {
+        *x ^= masking_key[(offset + i) % 4];
+    }
+}
+
+#[allow(unused_variables)]
 pub(crate) fn rng_fill(buf: &mut [u8]) {
     #[cfg(feature = "getrandom02")]
     {
@@ -0,0 +1,327 @@
+use crate::{RxRules, Utf8Validator, WsError, WsFrame, WsFrameHeader};
+
+/// Frame received by `WsRingRxFramer` with payload split into two segments
+///
+/// Second segment is non-empty only if payload wraps around end of ring
+/// buffer. Payload is already unmasked, but it isn't validated (text frames
+/// can contain invalid utf8, close payload isn't parsed).
+#[derive(Debug, PartialEq, Eq)]
+pub struct WsRingFrame<'b> {
+    pub fin: bool,
+    pub opcode: u8,
+    pub payload: (&'b [u8], &'b [u8]),
+}
+
+impl WsRingFrame<'_> {
+    /// Length of whole payload
+    pub fn len(&self) -> usize {
+        self.payload.0.len() + self.payload.1.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// If payload wraps around end of ring buffer
+    pub fn is_wrapped(&self) -> bool {
+        !self.payload.1.is_empty()
+    }
+}
+
+/// Framer used to receive websocket frames, backed by ring buffer
+///
+/// Alternative to `WsRxFramer` for high message rates: consumed frames only
+/// advance read position, so remaining data is never moved. Frames can be
+/// read as wrapped segments (`next_segments`) or as `WsFrame` (`next_frame`)
+/// that linearises buffer only when frame wraps. Fragmented messages aren't
+/// reassembled (`next_frame` returns their frames as `WsFrame::Fragment`) and
+/// extensions aren't supported, handshake should be done
+/// using `WsRxFramer` (bytes received after handshake must be copied over).
+pub struct WsRingRxFramer<'a> {
+    buf: &'a mut [u8],
+
+    /// Start of unconsumed data
+    read: usize,
+
+    /// Number of unconsumed bytes (can wrap around end of buffer)
+    len: usize,
+
+    rules: RxRules,
+
+    /// Opcode of fragmented message whose frames are read by `next_frame`
+    message_opcode: Option<u8>,
+
+    /// Validator for fragments of text message read by `next_frame`
+    utf8: Utf8Validator,
+}
+
+impl<'a> WsRingRxFramer<'a> {
+    /// Create framer that doesn't enforce masking of received frames
+    ///
+    /// `new_server` or `new_client` should be preferred
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            read: 0,
+            len: 0,
+            rules: RxRules::default(),
+            message_opcode: None,
+            utf8: Utf8Validator::new(),
+        }
+    }
+
+    /// Create framer used by server (received frames must be masked)
+    pub fn new_server(buf: &'a mut [u8]) -> Self {
+        Self {
+            rules: RxRules {
+                expect_mask: Some(true),
+                ..RxRules::default()
+            },
+            ..Self::new(buf)
+        }
+    }
+
+    /// Create framer used by client (received frames must not be masked)
+    pub fn new_client(buf: &'a mut [u8]) -> Self {
+        Self {
+            rules: RxRules {
+                expect_mask: Some(false),
+                ..RxRules::default()
+            },
+            ..Self::new(buf)
+        }
+    }
+
+    /// Set maximum payload length of single data frame (unlimited by default)
+    ///
+    /// Frames (including header) can't be larger than internal buffer.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.rules.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Set maximum payload length of control frame (see
+    /// `WsRxFramer::with_max_control_len`)
+    pub fn with_max_control_len(mut self, max_control_len: usize) -> Self {
+        self.rules.max_control_len = max_control_len;
+        self
+    }
+
+    /// Enable or disable strict validation of frame headers (see
+    /// `WsRxFramer::with_strict`)
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.rules.strict = strict;
+        self
+    }
+
+    /// Parse next frame and return its payload as (possibly wrapped) segments
+    pub fn next_segments(&mut self) -> Result<Option<WsRingFrame<'_>>, WsError> {
+        let Some(header) = self.next_header()? else {
+            return Ok(None);
+        };
+
+        let start = (self.read + header.offset) % self.buf.len();
+        self.consume(header.offset + header.payload_len);
+
+        let first_len = header.payload_len.min(self.buf.len() - start);
+        let (wrapped, tail) = self.buf.split_at_mut(start);
+        let first = &mut tail[..first_len];
+        let second = &mut wrapped[..header.payload_len - first_len];
+        if header.mask {
+            crate::apply_mask(first, header.masking_key, 0);
+            crate::apply_mask(second, header.masking_key, first_len);
+        }
+
+        Ok(Some(WsRingFrame {
+            fin: header.fin,
+            opcode: header.opcode,
+            payload: (first, second),
+        }))
+    }
+
+    /// Parse next frame
+    ///
+    /// If frame wraps around end of ring buffer, unconsumed data is moved to
+    /// start of buffer (free space isn't copied).
+    pub fn next_frame(&mut self) -> Result<Option<WsFrame<'_>>, WsError> {
+        let Some(header) = self.next_header()? else {
+            return Ok(None);
+        };
+
+        let frame_len = header.offset + header.payload_len;
+        if self.read + frame_len > self.buf.len() {
+            self.linearise();
+        }
+
+        let start = self.read + header.offset;
+        self.consume(frame_len);
+
+        WsFrame::from_data_tracked(
+            &header,
+            &mut self.buf[start..start + header.payload_len],
+            &mut self.message_opcode,
+            &mut self.utf8,
+        )
+        .map(Some)
+    }
+
+    /// Free space of ring buffer (data should be written at its start)
+    ///
+    /// Returned slice ends at end of buffer or at start of unconsumed data, so
+    /// after it's filled, next call can return free space at buffer start.
+    pub fn mut_buf(&mut self) -> &mut [u8] {
+        let write = (self.read + self.len) % self.buf.len().max(1);
+        let end = match write < self.read || self.len == self.buf.len() {
+            true => self.read,
+            false => self.buf.len(),
+        };
+
+        &mut self.buf[write..end]
+    }
+
+    pub fn revolve_write_offset(&mut self, n: usize) {
+        self.len += n;
+    }
+
+    /// Parse and validate header of next frame, returns `None` if whole frame
+    /// isn't received yet
+    fn next_header(&self) -> Result<Option<WsFrameHeader>, WsError> {
+        // header can wrap, so it's copied out of ring buffer
+        let mut data = [0; 14];
+        let n = self.len.min(data.len());
+        let first = n.min(self.buf.len() - self.read);
+        data[..first].copy_from_slice(&self.buf[self.read..self.read + first]);
+        data[first..n].copy_from_slice(&self.buf[..n - first]);
+
+        let Some(header) = WsFrameHeader::parse(&data[..n], self.rules.strict)? else {
+            return Ok(None);
+        };
+
+        // extensions aren't supported, so no rsv bits are claimed
+        header.validate(&self.rules, 0)?;
+        if header.payload_len > self.buf.len().saturating_sub(header.offset) {
+            return Err(WsError::FrameTooLarge);
+        }
+
+        if self.len < header.offset + header.payload_len {
+            return Ok(None);
+        }
+
+        Ok(Some(header))
+    }
+
+    /// Move unconsumed data (wrapped around end of buffer) to its start
+    fn linearise(&mut self) {
+        let first = (self.buf.len() - self.read).min(self.len);
+        let wrapped = self.len - first;
+
+        // [wrapped][first] is rotated into [first][wrapped]
+        self.buf.copy_within(self.read..self.read + first, wrapped);
+        self.buf[..self.len].rotate_left(wrapped);
+        self.read = 0;
+    }
+
+    /// Advance read position by `n` bytes
+    fn consume(&mut self, n: usize) {
+        self.len -= n;
+        self.read = match self.len {
+            0 => 0,
+            _ => (self.read + n) % self.buf.len(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receive(rx: &mut WsRingRxFramer<'_>, mut data: &[u8]) {
+        while !data.is_empty() {
+            let buf = rx.mut_buf();
+            let n = buf.len().min(data.len());
+            assert!(n > 0, "ring buffer full");
+
+            buf[..n].copy_from_slice(&data[..n]);
+            rx.revolve_write_offset(n);
+            data = &data[n..];
+        }
+    }
+
+    #[test]
+    fn ring_segments() {
+        let mut buf = [0; 16];
+        let mut rx = WsRingRxFramer::new_server(&mut buf);
+
+        // first frame is followed by start of next header
+        receive(&mut rx, &[0x82, 0x82, 0, 0, 0, 0, 1, 2, 0x81]);
+        let frame = rx.next_segments().unwrap().unwrap();
+        assert_eq!(frame.payload, (&[1, 2][..], &[][..]));
+        assert!(!frame.is_wrapped());
+
+        receive(&mut rx, &[0x84, 0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(rx.next_segments(), Ok(None));
+
+        // payload "abcd" masked with 01 02 03 04 wraps after second byte
+        receive(&mut rx, &[b'a' ^ 1, b'b' ^ 2, b'c' ^ 3, b'd' ^ 4]);
+        let frame = rx.next_segments().unwrap().unwrap();
+        assert!(frame.is_wrapped());
+        assert_eq!(
+            (frame.fin, frame.opcode, frame.payload),
+            (true, 1, (&b"ab"[..], &b"cd"[..]))
+        );
+    }
+
+    #[test]
+    fn ring_next_frame() {
+        let mut buf = [0; 16];
+        let mut rx = WsRingRxFramer::new_client(&mut buf);
+
+        // frames are consumed without moving remaining data
+        receive(
+            &mut rx,
+            &[0x81, 0x01, b'a', 0x89, 0x01, b'p', 0x81, 0x03, b'x'],
+        );
+        assert_eq!(rx.next_frame(), Ok(Some(WsFrame::Text("a"))));
+        assert_eq!(rx.next_frame(), Ok(Some(WsFrame::Ping(b"p"))));
+        assert_eq!(rx.next_frame(), Ok(None));
+
+        // wrapped frame is linearised
+        receive(&mut rx, b"yz\x82\x06abc");
+        assert_eq!(rx.next_frame(), Ok(Some(WsFrame::Text("xyz"))));
+        receive(&mut rx, b"def");
+        assert_eq!(rx.next_frame(), Ok(Some(WsFrame::Binary(b"abcdef"))));
+        assert_eq!(rx.mut_buf().len(), 16);
+
+        receive(&mut rx, &[0x82, 0x7E, 0x00, 0x7E]);
+        assert_eq!(rx.next_frame(), Err(WsError::FrameTooLarge));
+
+        let mut buf = [0; 16];
+        let mut rx = WsRingRxFramer::new_client(&mut buf);
+        receive(&mut rx, &[0x81, 0x81, 0, 0, 0, 0, b'a']);
+        assert_eq!(rx.next_frame(), Err(WsError::MaskedFrame));
+
+        // same header validation as `WsRxFramer`
+        let mut buf = [0; 16];
+        let mut rx = WsRingRxFramer::new_client(&mut buf).with_max_control_len(1);
+        receive(&mut rx, &[0x89, 0x02, b'p', b'q']);
+        assert_eq!(rx.next_frame(), Err(WsError::ControlFrameTooLarge));
+
+        let mut buf = [0; 16];
+        let mut rx = WsRingRxFramer::new_client(&mut buf);
+        receive(&mut rx, &[0xC1, 0x00]);
+        assert_eq!(rx.next_frame(), Err(WsError::UnexpectedRsv));
+
+        // "ż" split between fragments of text message
+        let mut buf = [0; 16];
+        let mut rx = WsRingRxFramer::new_client(&mut buf);
+        receive(&mut rx, &[0x01, 0x01, 0xC5, 0x80, 0x01, 0xBC]);
+        assert!(matches!(
+            rx.next_frame(),
+            Ok(Some(WsFrame::Fragment { opcode: 1, .. }))
+        ));
+        assert!(matches!(
+            rx.next_frame(),
+            Ok(Some(WsFrame::Fragment { fin: true, .. }))
+        ));
+    }
+}
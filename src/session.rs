@@ -0,0 +1,213 @@
+use crate::{CloseCode, WsError, WsFrame, WsRxFramer, WsTxFramer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// State of websocket connection (RFC 6455 section 7)
+pub enum WsState {
+    /// Data can be sent and received
+    Open,
+
+    /// Close frame was sent, waiting for peer to reply with close frame
+    Closing,
+
+    /// Close frames were exchanged, transport can be shut down
+    Closed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Side that started closing handshake
+pub enum CloseInitiator {
+    Local,
+    Remote,
+}
+
+#[derive(Debug)]
+/// Frame received by `WsSession` with data that must be sent in response
+pub struct SessionFrame<'b> {
+    pub frame: WsFrame<'b>,
+
+    /// Packet that should be written to transport (close frame echo)
+    pub reply: Option<&'b [u8]>,
+}
+
+/// Sans-IO websocket connection layered on top of rx and tx framers
+///
+/// Tracks connection state and handles closing handshake: received close
+/// frame is echoed automatically and frames cannot be sent after close frame.
+/// Handshake should be done before framers are passed to session.
+pub struct WsSession<'a> {
+    rx: WsRxFramer<'a>,
+    tx: WsTxFramer<'a>,
+    state: WsState,
+    initiator: Option<CloseInitiator>,
+}
+
+impl<'a> WsSession<'a> {
+    pub fn new(rx: WsRxFramer<'a>, tx: WsTxFramer<'a>) -> Self {
+        Self {
+            rx,
+            tx,
+            state: WsState::Open,
+            initiator: None,
+        }
+    }
+
+    pub fn state(&self) -> WsState {
+        self.state
+    }
+
+    /// Side that started closing handshake (`None` if connection is open)
+    pub fn close_initiator(&self) -> Option<CloseInitiator> {
+        self.initiator
+    }
+
+    /// If underlying transport can be shut down
+    ///
+    /// Server should close TCP connection first (RFC 6455 section 7.1.1), so
+    /// client should wait for it (with timeout) after this returns `true`.
+    pub fn can_shutdown(&self) -> bool {
+        self.state == WsState::Closed
+    }
+
+    /// Free space of rx framer buffer (see `WsRxFramer::mut_buf`)
+    pub fn mut_buf(&mut self) -> &mut [u8] {
+        self.rx.mut_buf()
+    }
+
+    pub fn revolve_write_offset(&mut self, n: usize) {
+        self.rx.revolve_write_offset(n);
+    }
+
+    /// Parse next received frame
+    ///
+    /// Received close frame moves connection to `Closed` state. If close was
+    /// initiated by peer, close frame with the same status code is generated
+    /// as `reply`. Frames received after connection is closed are ignored.
+    pub fn recv(&mut self) -> Result<Option<SessionFrame<'_>>, WsError> {
+        if self.state == WsState::Closed {
+            return Ok(None);
+        }
+
+        let Some(parsed) = self.rx.next_frame()? else {
+            return Ok(None);
+        };
+
+        let frame = self.rx.parsed_frame(parsed)?;
+        let WsFrame::Close(code, _) = frame else {
+            return Ok(Some(SessionFrame { frame, reply: None }));
+        };
+
+        let reply = match self.state {
+            WsState::Open => {
+                self.initiator = Some(CloseInitiator::Remote);
+                Some(self.tx.frame(WsFrame::Close(code, ""))?)
+            }
+            _ => None,
+        };
+
+        self.state = WsState::Closed;
+        Ok(Some(SessionFrame { frame, reply }))
+    }
+
+    /// Generate frame (fails if close frame was already sent)
+    ///
+    /// Close frames should be sent using `close`.
+    pub fn send<'b>(&'b mut self, frame: WsFrame<'_>) -> Result<&'b [u8], WsError> {
+        if let WsFrame::Close(code, reason) = frame {
+            return self.close(code, reason);
+        }
+
+        if self.state != WsState::Open {
+            return Err(WsError::ConnectionClosed);
+        }
+
+        self.tx.frame(frame)
+    }
+
+    /// Start closing handshake (moves connection to `Closing` state)
+    pub fn close<'b>(&'b mut self, code: CloseCode, reason: &str) -> Result<&'b [u8], WsError> {
+        if self.state != WsState::Open {
+            return Err(WsError::ConnectionClosed);
+        }
+
+        let data = self.tx.close(code, reason)?;
+        self.state = WsState::Closing;
+        self.initiator = Some(CloseInitiator::Local);
+        Ok(data)
+    }
+
+    /// Mark transport as closed by peer (e.g. read returned 0 bytes)
+    pub fn transport_closed(&mut self) {
+        self.state = WsState::Closed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_session<'a>(rx_buf: &'a mut [u8], tx_buf: &'a mut [u8]) -> WsSession<'a> {
+        WsSession::new(WsRxFramer::new(rx_buf), WsTxFramer::new(false, tx_buf))
+    }
+
+    fn receive(session: &mut WsSession<'_>, data: &[u8]) {
+        session.mut_buf()[..data.len()].copy_from_slice(data);
+        session.revolve_write_offset(data.len());
+    }
+
+    #[test]
+    fn remote_close() {
+        let (mut rx_buf, mut tx_buf) = ([0; 64], [0; 64]);
+        let mut session = new_session(&mut rx_buf, &mut tx_buf);
+        receive(&mut session, &[0x81, 0x01, b'a', 0x88, 0x02, 0x03, 0xE9]);
+
+        let res = session.recv().unwrap().unwrap();
+        assert_eq!((res.frame, res.reply), (WsFrame::Text("a"), None));
+
+        let res = session.recv().unwrap().unwrap();
+        assert_eq!(res.frame, WsFrame::Close(CloseCode::GoingAway, ""));
+        assert_eq!(res.reply, Some(&[0x88, 0x02, 0x03, 0xE9][..]));
+
+        assert_eq!(session.state(), WsState::Closed);
+        assert_eq!(session.close_initiator(), Some(CloseInitiator::Remote));
+        assert!(session.can_shutdown());
+        assert_eq!(
+            session.send(WsFrame::Text("b")),
+            Err(WsError::ConnectionClosed)
+        );
+
+        // close without status code is echoed with empty payload
+        let (mut rx_buf, mut tx_buf) = ([0; 64], [0; 64]);
+        let mut session = new_session(&mut rx_buf, &mut tx_buf);
+        receive(&mut session, &[0x88, 0x00]);
+        let res = session.recv().unwrap().unwrap();
+        assert_eq!(res.reply, Some(&[0x88, 0x00][..]));
+    }
+
+    #[test]
+    fn local_close() {
+        let (mut rx_buf, mut tx_buf) = ([0; 64], [0; 64]);
+        let mut session = new_session(&mut rx_buf, &mut tx_buf);
+
+        assert_eq!(
+            session.close(CloseCode::Normal, ""),
+            Ok(&[0x88, 0x02, 0x03, 0xE8][..])
+        );
+        assert_eq!(session.state(), WsState::Closing);
+        assert_eq!(
+            session.send(WsFrame::Ping(&[])),
+            Err(WsError::ConnectionClosed)
+        );
+        assert!(!session.can_shutdown());
+
+        // data received before peer replies is still returned
+        receive(&mut session, &[0x82, 0x01, 0x01, 0x88, 0x02, 0x03, 0xE8]);
+        let res = session.recv().unwrap().unwrap();
+        assert_eq!(res.frame, WsFrame::Binary(&[1]));
+
+        let res = session.recv().unwrap().unwrap();
+        assert_eq!(res.frame, WsFrame::Close(CloseCode::Normal, ""));
+        assert_eq!(res.reply, None);
+        assert_eq!(session.state(), WsState::Closed);
+        assert_eq!(session.close_initiator(), Some(CloseInitiator::Local));
+    }
+}
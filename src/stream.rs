@@ -0,0 +1,173 @@
+use crate::{driver::WsDriver, AcceptOptions, ConnectionError, WsFrame, WsUrl};
+
+/// Generates blocking stream wrapper for transport with given io traits
+///
+/// `std::io` and `embedded_io` traits share method names (`read`,
+/// `write_all`, `flush`), only trait bounds and error type differ.
+macro_rules! ws_stream_impl {
+    ($(#[$meta:meta])* $name:ident, $read:path, $write:path, $error:ty) => {
+        $(#[$meta])*
+        pub struct $name<'a, S> {
+            io: S,
+            driver: WsDriver<'a>,
+        }
+
+        impl<'a, S: $read + $write> $name<'a, S> {
+            /// Send upgrade request to server and wait for valid handshake response
+            pub fn connect(
+                io: S,
+                url: &WsUrl<'_>,
+                rx_buf: &'a mut [u8],
+                tx_buf: &'a mut [u8],
+            ) -> Result<Self, ConnectionError<$error>> {
+                let mut stream = Self {
+                    io,
+                    driver: WsDriver::client(rx_buf, tx_buf),
+                };
+
+                let request = stream.driver.upgrade_request(url)?;
+                Self::write(&mut stream.io, request)?;
+                while !stream.driver.handshake_response()? {
+                    Self::read(&mut stream.io, &mut stream.driver)?;
+                }
+
+                Ok(stream)
+            }
+
+            /// Wait for client upgrade request and respond to it
+            ///
+            /// Rejected request is answered with error response before returning error
+            pub fn accept(
+                io: S,
+                options: &AcceptOptions<'_>,
+                rx_buf: &'a mut [u8],
+                tx_buf: &'a mut [u8],
+            ) -> Result<Self, ConnectionError<$error>> {
+                let mut stream = Self {
+                    io,
+                    driver: WsDriver::server(rx_buf, tx_buf),
+                };
+
+                loop {
+                    let Some((response, result)) = stream.driver.accept(options)? else {
+                        Self::read(&mut stream.io, &mut stream.driver)?;
+                        continue;
+                    };
+
+                    Self::write(&mut stream.io, response)?;
+                    result?;
+                    return Ok(stream);
+                }
+            }
+
+            /// Read next message (or control frame)
+            ///
+            /// Blocks until whole message is received, frames that are already
+            /// buffered are returned without reading from transport.
+            pub fn read_message(&mut self) -> Result<WsFrame<'_>, ConnectionError<$error>> {
+                loop {
+                    if let Some(parsed) = self.driver.next_frame()? {
+                        return Ok(self.driver.parsed_frame(parsed)?);
+                    }
+
+                    Self::read(&mut self.io, &mut self.driver)?;
+                }
+            }
+
+            /// Write message (or control frame) and flush transport
+            pub fn write_message(
+                &mut self,
+                frame: WsFrame<'_>,
+            ) -> Result<(), ConnectionError<$error>> {
+                let data = self.driver.frame(frame)?;
+                Self::write(&mut self.io, data)
+            }
+
+            /// Return inner transport
+            pub fn into_inner(self) -> S {
+                self.io
+            }
+
+            /// Read from transport into free space of driver buffer
+            fn read(io: &mut S, driver: &mut WsDriver<'_>) -> Result<(), ConnectionError<$error>> {
+                let n = io.read(driver.read_buf()?).map_err(ConnectionError::Io)?;
+                driver.received(n)
+            }
+
+            /// Write whole data to transport and flush it
+            fn write(io: &mut S, data: &[u8]) -> Result<(), ConnectionError<$error>> {
+                io.write_all(data).map_err(ConnectionError::Io)?;
+                io.flush().map_err(ConnectionError::Io)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+ws_stream_impl!(
+    /// Blocking websocket stream over `std::io` transport (e.g. `TcpStream`)
+    ///
+    /// Uses caller-provided buffers, fragmented messages are reassembled, so
+    /// `rx_buf` must be large enough to hold whole message.
+    WsStream,
+    std::io::Read,
+    std::io::Write,
+    std::io::Error
+);
+
+#[cfg(feature = "embedded-io")]
+ws_stream_impl!(
+    /// Blocking websocket stream over `embedded-io` transport
+    ///
+    /// Same as `WsStream`, but for `no_std` transports.
+    WsEmbeddedStream,
+    embedded_io::Read,
+    embedded_io::Write,
+    S::Error
+);
+
+#[cfg(all(test, any(feature = "std", feature = "embedded-io")))]
+mod tests {
+    use super::*;
+    use crate::driver::mock::MockIo;
+
+    /// Whole upgrade request and two frames (delivered in single read)
+    const INPUT: &[u8] = b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+        Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+        Sec-WebSocket-Version: 13\r\n\r\n\x81\x85\0\0\0\0hello\x82\x82\0\0\0\0\x01\x02";
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn std_accept_and_read_buffered() {
+        let mut rx_buf = [0; 256];
+        let mut tx_buf = [0; 256];
+        let options = AcceptOptions::default();
+        let io = MockIo::new(INPUT, usize::MAX);
+        let mut stream = WsStream::accept(io, &options, &mut rx_buf, &mut tx_buf).unwrap();
+
+        assert_eq!(stream.read_message().unwrap(), WsFrame::Text("hello"));
+        assert_eq!(stream.read_message().unwrap(), WsFrame::Binary(&[1, 2]));
+        assert!(matches!(
+            stream.read_message(),
+            Err(ConnectionError::Closed)
+        ));
+        stream.write_message(WsFrame::Pong(b"p")).unwrap();
+        stream.into_inner().check_output(b"\r\n\r\n\x8A\x01p");
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn accept_and_read_buffered() {
+        let mut rx_buf = [0; 256];
+        let mut tx_buf = [0; 256];
+        let options = AcceptOptions::default();
+        let io = MockIo::new(INPUT, usize::MAX);
+        let mut stream = WsEmbeddedStream::accept(io, &options, &mut rx_buf, &mut tx_buf).unwrap();
+
+        assert_eq!(stream.read_message(), Ok(WsFrame::Text("hello")));
+        assert_eq!(stream.read_message(), Ok(WsFrame::Binary(&[1, 2])));
+        assert_eq!(stream.read_message(), Err(ConnectionError::Closed));
+        stream.write_message(WsFrame::Pong(b"p")).unwrap();
+        stream.into_inner().check_output(b"\r\n\r\n\x8A\x01p");
+    }
+}
@@ -14,6 +14,7 @@ pub struct WsUrl<'a> {
 
 impl WsUrl<'_> {
     /// Parse ws url string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str<'a>(ws_url: &'a str) -> Option<WsUrl<'a>> {
         let (secure, host_start_offset) = if ws_url.starts_with("ws://") {
             (false, 5)
@@ -36,12 +37,12 @@ impl WsUrl<'_> {
 
         let host = ws_url.get(host_start_offset..host_end.unwrap_or(ws_url.len()))?;
         let path = ws_url.get(host_end.unwrap_or(ws_url.len())..ws_url.len())?;
-        let path = if path.len() == 0 { "/" } else { path };
+        let path = if path.is_empty() { "/" } else { path };
 
         let mut host_split = host.split(':');
         let ip = host_split.next()?;
         let port = if let Some(port_str) = host_split.next() {
-            u16::from_str_radix(port_str, 10).ok()?
+            port_str.parse().ok()?
         } else {
             //default ports
             match secure {
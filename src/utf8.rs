@@ -0,0 +1,111 @@
+use crate::WsError;
+
+#[derive(Debug, Clone, Default)]
+/// Incremental utf-8 validator for payloads split into multiple chunks
+/// (for example fragmented text messages)
+///
+/// Code points split across chunk boundaries are handled correctly.
+pub struct Utf8Validator {
+    /// Bytes of code point that was cut at the end of previous chunk
+    partial: [u8; 4],
+
+    /// Number of stored partial code point bytes
+    partial_len: usize,
+}
+
+impl Utf8Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validate next chunk of data
+    ///
+    /// Fails as soon as invalid sequence is found, even if it isn't finished yet
+    pub fn feed(&mut self, mut data: &[u8]) -> Result<(), WsError> {
+        if self.partial_len > 0 {
+            let needed = Self::sequence_len(self.partial[0]) - self.partial_len;
+            let n = needed.min(data.len());
+            self.partial[self.partial_len..self.partial_len + n].copy_from_slice(&data[..n]);
+            self.partial_len += n;
+            data = &data[n..];
+
+            match core::str::from_utf8(&self.partial[..self.partial_len]) {
+                Ok(_) => self.partial_len = 0,
+                Err(e) if e.error_len().is_none() => return Ok(()),
+                Err(_) => return Err(WsError::InvalidUtf8),
+            }
+        }
+
+        match core::str::from_utf8(data) {
+            Ok(_) => Ok(()),
+            Err(e) if e.error_len().is_none() => {
+                let rest = &data[e.valid_up_to()..];
+                self.partial[..rest.len()].copy_from_slice(rest);
+                self.partial_len = rest.len();
+
+                Ok(())
+            }
+            Err(_) => Err(WsError::InvalidUtf8),
+        }
+    }
+
+    /// Check if all fed data ended on code point boundary and reset validator
+    pub fn finish(&mut self) -> Result<(), WsError> {
+        let partial_len = core::mem::take(&mut self.partial_len);
+        match partial_len {
+            0 => Ok(()),
+            _ => Err(WsError::InvalidUtf8),
+        }
+    }
+
+    /// Reset validator state (discard partial code point)
+    pub fn reset(&mut self) {
+        self.partial_len = 0;
+    }
+
+    /// Length of utf-8 sequence (only for valid leading bytes)
+    fn sequence_len(first: u8) -> usize {
+        match first {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            _ => 4,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_split_code_points() {
+        let text = "zażółć 🦀".as_bytes();
+        for i in 0..text.len() {
+            for j in i..text.len() {
+                let mut validator = Utf8Validator::new();
+                assert_eq!(validator.feed(&text[..i]), Ok(()));
+                assert_eq!(validator.feed(&text[i..j]), Ok(()));
+                assert_eq!(validator.feed(&text[j..]), Ok(()));
+                assert_eq!(validator.finish(), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn validate_invalid_sequences() {
+        let mut validator = Utf8Validator::new();
+        assert_eq!(validator.feed(&[0xF0, 0x9F]), Ok(()));
+        assert_eq!(validator.feed(&[0x28]), Err(WsError::InvalidUtf8));
+
+        // fail fast on sequence that cannot be valid (encoded surrogate)
+        let mut validator = Utf8Validator::new();
+        assert_eq!(
+            validator.feed(&[b'a', 0xED, 0xA0]),
+            Err(WsError::InvalidUtf8)
+        );
+
+        let mut validator = Utf8Validator::new();
+        assert_eq!(validator.feed(&[0xE2, 0x82]), Ok(()));
+        assert_eq!(validator.finish(), Err(WsError::InvalidUtf8));
+    }
+}